@@ -0,0 +1,110 @@
+// Arithmetic for puzzle values. With the `checked-arithmetic` feature every operation is
+// checked and overflow comes back as AocError::Overflow, without it these are the plain
+// operators, which wrap in release builds.
+use crate::error::AocError;
+use std::ops::{Add, Mul, Sub};
+
+pub trait Arithmetic:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    const ZERO: Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_abs(self) -> Option<Self>;
+    fn abs(self) -> Self;
+}
+
+macro_rules! impl_arithmetic {
+    (signed: $($signed:ty),+; unsigned: $($unsigned:ty),+) => {
+        $(impl_arithmetic!(@impl $signed, |value: $signed| value.checked_abs(), |value: $signed| value.abs());)+
+        $(impl_arithmetic!(@impl $unsigned, |value: $unsigned| Some(value), |value: $unsigned| value);)+
+    };
+    (@impl $t:ty, $checked_abs:expr, $abs:expr) => {
+        impl Arithmetic for $t {
+            const ZERO: Self = 0;
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+            fn checked_abs(self) -> Option<Self> {
+                $checked_abs(self)
+            }
+            fn abs(self) -> Self {
+                $abs(self)
+            }
+        }
+    };
+}
+
+impl_arithmetic!(signed: i32, i64, i128, isize; unsigned: u32, u64, u128, usize);
+
+// What solvers widen their intermediate values to, so that a step past isize::MAX that the
+// search throws away anyway isn't reported as overflow. i128 holds any sum, product or
+// concatenation of two isize values, but longer chains still go through add and mul.
+#[cfg(feature = "checked-arithmetic")]
+pub type Wide = i128;
+#[cfg(not(feature = "checked-arithmetic"))]
+pub type Wide = isize;
+
+pub const CHECKED: bool = cfg!(feature = "checked-arithmetic");
+
+fn checked<T>(checked: impl FnOnce() -> Option<T>, unchecked: impl FnOnce() -> T) -> Result<T, AocError> {
+    if CHECKED {
+        checked().ok_or(AocError::Overflow)
+    } else {
+        Ok(unchecked())
+    }
+}
+
+pub fn add<T: Arithmetic>(a: T, b: T) -> Result<T, AocError> {
+    checked(|| a.checked_add(b), || a + b)
+}
+
+pub fn sub<T: Arithmetic>(a: T, b: T) -> Result<T, AocError> {
+    checked(|| a.checked_sub(b), || a - b)
+}
+
+pub fn mul<T: Arithmetic>(a: T, b: T) -> Result<T, AocError> {
+    checked(|| a.checked_mul(b), || a * b)
+}
+
+pub fn abs<T: Arithmetic>(value: T) -> Result<T, AocError> {
+    checked(|| value.checked_abs(), || value.abs())
+}
+
+pub fn sum<T: Arithmetic>(values: impl IntoIterator<Item = T>) -> Result<T, AocError> {
+    values.into_iter().try_fold(T::ZERO, add)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arith::{abs, add, mul, sub, sum};
+
+    #[test]
+    fn in_range() {
+        assert_eq!(add(2isize, 3), Ok(5));
+        assert_eq!(sub(2usize, 2), Ok(0));
+        assert_eq!(mul(-4i64, 3), Ok(-12));
+        assert_eq!(abs(-7isize), Ok(7));
+        assert_eq!(abs(7usize), Ok(7));
+        assert_eq!(sum([1u32, 2, 3]), Ok(6));
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn overflow() {
+        use crate::error::AocError;
+
+        assert_eq!(add(usize::MAX, 1), Err(AocError::Overflow));
+        assert_eq!(sub(0usize, 1), Err(AocError::Overflow));
+        assert_eq!(mul(isize::MAX / 2 + 1, 2), Err(AocError::Overflow));
+        assert_eq!(abs(isize::MIN), Err(AocError::Overflow));
+        assert_eq!(sum([u32::MAX, 1]), Err(AocError::Overflow));
+    }
+}
@@ -0,0 +1,417 @@
+use crate::common;
+use crate::graph::{self, Coordinate, Index};
+use std::marker::PhantomData;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(WORD_BITS)],
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn contains(&self, bit: usize) -> bool {
+        bit < self.capacity && self.words[bit / WORD_BITS] & (1 << (bit % WORD_BITS)) != 0
+    }
+
+    // Returns true if the bit was not already set, mirroring HashSet::insert
+    pub fn insert(&mut self, bit: usize) -> bool {
+        assert!(bit < self.capacity, "bit {} outside capacity {}", bit, self.capacity);
+        let word = &mut self.words[bit / WORD_BITS];
+        let mask = 1 << (bit % WORD_BITS);
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    pub fn remove(&mut self, bit: usize) -> bool {
+        if bit >= self.capacity {
+            return false;
+        }
+        let word = &mut self.words[bit / WORD_BITS];
+        let mask = 1 << (bit % WORD_BITS);
+        let removed = *word & mask != 0;
+        *word &= !mask;
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+}
+
+// Anything that can name a cell in a grid.
+pub trait Cell {
+    fn cell(&self) -> Option<Index>;
+}
+
+impl Cell for Index {
+    fn cell(&self) -> Option<Index> {
+        Some(*self)
+    }
+}
+
+impl Cell for Coordinate {
+    fn cell(&self) -> Option<Index> {
+        self.try_into().ok()
+    }
+}
+
+impl Cell for common::Point {
+    fn cell(&self) -> Option<Index> {
+        Some(Index { x: self.x, y: self.y })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Dimensions {
+    width: usize,
+    height: usize,
+}
+
+impl Dimensions {
+    fn offset<C: Cell>(&self, cell: &C) -> Option<usize> {
+        let Index { x, y } = cell.cell()?;
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    fn index_of(&self, offset: usize) -> Index {
+        Index {
+            x: offset % self.width,
+            y: offset / self.width,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GridSet {
+    dimensions: Dimensions,
+    bits: BitSet,
+}
+
+impl GridSet {
+    pub fn new(width: usize, height: usize) -> Self {
+        let dimensions = Dimensions { width, height };
+        Self {
+            dimensions,
+            bits: BitSet::new(dimensions.len()),
+        }
+    }
+
+    pub fn for_grid<T>(grid: &[Vec<T>]) -> Self {
+        Self::new(grid.first().map_or(0, Vec::len), grid.len())
+    }
+
+    pub fn contains<C: Cell>(&self, cell: &C) -> bool {
+        match self.dimensions.offset(cell) {
+            Some(offset) => self.bits.contains(offset),
+            None => false,
+        }
+    }
+
+    // None when the cell is off the grid
+    pub fn insert<C: Cell>(&mut self, cell: &C) -> Option<bool> {
+        let offset = self.dimensions.offset(cell)?;
+        Some(self.bits.insert(offset))
+    }
+
+    pub fn remove<C: Cell>(&mut self, cell: &C) -> bool {
+        match self.dimensions.offset(cell) {
+            Some(offset) => self.bits.remove(offset),
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Index> + '_ {
+        self.bits.iter().map(|offset| self.dimensions.index_of(offset))
+    }
+}
+
+const CARDINAL_LAYERS: usize = 4;
+
+// Maps the four cardinal directions onto layers of a DirectionalGridSet. Direction types with
+// diagonals have no layer for them.
+pub trait Cardinal: Sized {
+    fn layer(&self) -> Option<usize>;
+    fn from_layer(layer: usize) -> Option<Self>;
+}
+
+macro_rules! impl_cardinal {
+    ($direction:ty) => {
+        impl Cardinal for $direction {
+            fn layer(&self) -> Option<usize> {
+                match self {
+                    Self::North => Some(0),
+                    Self::East => Some(1),
+                    Self::South => Some(2),
+                    Self::West => Some(3),
+                    _ => None,
+                }
+            }
+
+            fn from_layer(layer: usize) -> Option<Self> {
+                match layer {
+                    0 => Some(Self::North),
+                    1 => Some(Self::East),
+                    2 => Some(Self::South),
+                    3 => Some(Self::West),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_cardinal!(graph::Direction);
+impl_cardinal!(common::Direction);
+
+// A GridSet with an extra dimension for the four cardinal directions, for
+// tracking (direction, cell) states.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectionalGridSet<D> {
+    dimensions: Dimensions,
+    bits: BitSet,
+    directions: PhantomData<D>,
+}
+
+impl<D: Cardinal> DirectionalGridSet<D> {
+    pub fn new(width: usize, height: usize) -> Self {
+        let dimensions = Dimensions { width, height };
+        Self {
+            dimensions,
+            bits: BitSet::new(dimensions.len() * CARDINAL_LAYERS),
+            directions: PhantomData,
+        }
+    }
+
+    pub fn for_grid<T>(grid: &[Vec<T>]) -> Self {
+        Self::new(grid.first().map_or(0, Vec::len), grid.len())
+    }
+
+    fn offset<C: Cell>(&self, direction: &D, cell: &C) -> Option<usize> {
+        let offset = self.dimensions.offset(cell)?;
+        Some(direction.layer()? * self.dimensions.len() + offset)
+    }
+
+    pub fn contains<C: Cell>(&self, direction: &D, cell: &C) -> bool {
+        match self.offset(direction, cell) {
+            Some(offset) => self.bits.contains(offset),
+            None => false,
+        }
+    }
+
+    // None when the state can't be held, the cell being off the grid or the direction not one
+    // of the four cardinals
+    pub fn insert<C: Cell>(&mut self, direction: &D, cell: &C) -> Option<bool> {
+        let offset = self.offset(direction, cell)?;
+        Some(self.bits.insert(offset))
+    }
+
+    pub fn remove<C: Cell>(&mut self, direction: &D, cell: &C) -> bool {
+        match self.offset(direction, cell) {
+            Some(offset) => self.bits.remove(offset),
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (D, Index)> + '_ {
+        let cells = self.dimensions.len();
+        self.bits.iter().filter_map(move |offset| {
+            Some((
+                D::from_layer(offset / cells)?,
+                self.dimensions.index_of(offset % cells),
+            ))
+        })
+    }
+}
+
+// A dense per-cell map, used in place of HashMap<Coordinate, T> for scores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoreGrid<T> {
+    dimensions: Dimensions,
+    scores: Vec<Option<T>>,
+}
+
+impl<T> ScoreGrid<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        let dimensions = Dimensions { width, height };
+        let mut scores = Vec::with_capacity(dimensions.len());
+        scores.resize_with(dimensions.len(), || None);
+        Self { dimensions, scores }
+    }
+
+    pub fn for_grid<U>(grid: &[Vec<U>]) -> Self {
+        Self::new(grid.first().map_or(0, Vec::len), grid.len())
+    }
+
+    pub fn contains<C: Cell>(&self, cell: &C) -> bool {
+        self.get(cell).is_some()
+    }
+
+    pub fn get<C: Cell>(&self, cell: &C) -> Option<&T> {
+        self.scores.get(self.dimensions.offset(cell)?)?.as_ref()
+    }
+
+    // The score the cell had before, or None when the cell is off the grid
+    pub fn insert<C: Cell>(&mut self, cell: &C, score: T) -> Option<Option<T>> {
+        let offset = self.dimensions.offset(cell)?;
+        Some(self.scores[offset].replace(score))
+    }
+
+    pub fn remove<C: Cell>(&mut self, cell: &C) -> Option<T> {
+        let offset = self.dimensions.offset(cell)?;
+        self.scores[offset].take()
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.iter_mut().for_each(|score| *score = None);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Index, &T)> + '_ {
+        self.scores
+            .iter()
+            .enumerate()
+            .filter_map(|(offset, score)| Some((self.dimensions.index_of(offset), score.as_ref()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitset::{BitSet, DirectionalGridSet, GridSet, ScoreGrid};
+    use crate::graph::{Coordinate, Direction, Index};
+
+    #[test]
+    fn bitset_inserts_and_removes() {
+        let mut set = BitSet::new(130);
+        assert!(set.insert(0));
+        assert!(set.insert(64));
+        assert!(set.insert(129));
+        assert!(!set.insert(64));
+        assert!(set.contains(129));
+        assert!(!set.contains(1));
+        assert!(!set.contains(1000));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 64, 129]);
+        assert!(set.remove(64));
+        assert!(!set.remove(64));
+        assert_eq!(set.len(), 2);
+        set.clear();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn grid_set_accepts_any_cell_type() {
+        let mut set = GridSet::new(3, 2);
+        assert_eq!(set.insert(&Index { x: 2, y: 1 }), Some(true));
+        assert!(set.contains(&Coordinate { x: 2, y: 1 }));
+        assert!(!set.contains(&Coordinate { x: -1, y: 1 }));
+        assert!(!set.contains(&Index { x: 3, y: 0 }));
+        assert_eq!(set.insert(&Coordinate { x: 2, y: 1 }), Some(false));
+        assert_eq!(set.insert(&Coordinate { x: -1, y: 1 }), None);
+        assert_eq!(set.insert(&Index { x: 3, y: 0 }), None);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Index { x: 2, y: 1 }]);
+    }
+
+    #[test]
+    fn directional_grid_set_separates_directions() {
+        let mut set = DirectionalGridSet::new(4, 4);
+        let cell = Index { x: 1, y: 2 };
+        assert_eq!(set.insert(&Direction::East, &cell), Some(true));
+        assert_eq!(set.insert(&Direction::East, &cell), Some(false));
+        assert!(set.contains(&Direction::East, &cell));
+        assert!(!set.contains(&Direction::West, &cell));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![(Direction::East, cell)]);
+        set.clear();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn directional_grid_set_has_no_diagonals() {
+        let mut set = DirectionalGridSet::new(4, 4);
+        let cell = Index { x: 1, y: 2 };
+        assert_eq!(set.insert(&Direction::NorthEast, &cell), None);
+        assert!(!set.contains(&Direction::NorthEast, &cell));
+        assert!(!set.remove(&Direction::NorthEast, &cell));
+        assert_eq!(set.insert(&Direction::East, &Index { x: 4, y: 0 }), None);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn score_grid_behaves_like_a_map() {
+        let mut scores = ScoreGrid::new(2, 2);
+        let cell = Coordinate { x: 1, y: 0 };
+        assert_eq!(scores.insert(&cell, 10), Some(None));
+        assert_eq!(scores.insert(&cell, 5), Some(Some(10)));
+        assert_eq!(scores.insert(&Coordinate { x: 2, y: 0 }, 1), None);
+        assert_eq!(scores.get(&cell), Some(&5));
+        assert_eq!(scores.get(&Coordinate { x: 0, y: 0 }), None);
+        assert_eq!(scores.iter().collect::<Vec<_>>(), vec![(Index { x: 1, y: 0 }, &5)]);
+        assert_eq!(scores.remove(&cell), Some(5));
+        assert!(!scores.contains(&cell));
+    }
+}
@@ -0,0 +1,81 @@
+// A step budget, so fuzzing can stop solvers that take too long instead of hanging. Solvers
+// call `step` once per unit of work in their main loops, which costs nothing until someone
+// sets a budget with `limit`.
+use crate::error::AocError;
+use std::cell::Cell;
+
+thread_local! {
+    static REMAINING: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+pub fn step() -> Result<(), AocError> {
+    REMAINING.with(|remaining| match remaining.get() {
+        None => Ok(()),
+        Some(0) => Err(AocError::OutOfSteps),
+        Some(steps) => {
+            remaining.set(Some(steps - 1));
+            Ok(())
+        }
+    })
+}
+
+// Runs `f` with `steps` steps to spend, putting back whatever budget was there before
+pub fn limit<T>(steps: usize, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<usize>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            REMAINING.set(self.0);
+        }
+    }
+
+    let _restore = Restore(REMAINING.replace(Some(steps)));
+    f()
+}
+
+// What's left of the current thread's budget, None when there isn't one
+pub fn remaining() -> Option<usize> {
+    REMAINING.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::{limit, remaining, step};
+    use crate::error::AocError;
+
+    fn spin(steps: usize) -> Result<(), AocError> {
+        (0..steps).try_for_each(|_| step())
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        assert_eq!(spin(10_000), Ok(()));
+    }
+
+    #[test]
+    fn runs_out() {
+        assert_eq!(limit(10, || spin(10)), Ok(()));
+        assert_eq!(limit(10, || spin(11)), Err(AocError::OutOfSteps));
+        assert_eq!(spin(100), Ok(()));
+    }
+
+    #[test]
+    fn nests() {
+        let left = limit(10, || {
+            let inner = limit(3, || spin(5));
+            (inner, spin(10))
+        });
+        assert_eq!(left, (Err(AocError::OutOfSteps), Ok(())));
+    }
+
+    #[test]
+    fn says_what_is_left() {
+        let left = limit(10, || {
+            let _ = spin(4);
+            let after_four = remaining();
+            let _ = spin(20);
+            (after_four, remaining())
+        });
+        assert_eq!(left, (Some(6), Some(0)));
+        assert_eq!(remaining(), None);
+    }
+}
@@ -0,0 +1,165 @@
+// Cycle detection for iterated functions x, f(x), f(f(x)), ...
+// A sequence that repeats has a tail of `tail` states before it enters a loop of `length`
+// states, so state n (n >= tail) is the same as state tail + (n - tail) % length.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub tail: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    // The smallest step that lands on the same state as step n
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.tail {
+            n
+        } else {
+            self.tail + (n - self.tail) % self.length
+        }
+    }
+}
+
+fn advance<S, F: FnMut(&S) -> S>(state: &S, step: &mut F, n: usize) -> S
+where
+    S: Clone,
+{
+    let mut state = state.clone();
+    for _ in 0..n {
+        state = step(&state);
+    }
+    state
+}
+
+// Works out the tail once the cycle length is known, by running two states `length` apart
+// until they meet.
+fn find_tail<S, F>(start: &S, step: &mut F, length: usize) -> usize
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = start.clone();
+    let mut hare = advance(start, step, length);
+    let mut tail = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail += 1;
+    }
+    tail
+}
+
+// Floyd's tortoise and hare. Never returns if the sequence doesn't repeat.
+pub fn floyd<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        hare = step(&hare);
+    }
+
+    let mut length = 1;
+    let mut runner = step(&tortoise);
+    while runner != tortoise {
+        runner = step(&runner);
+        length += 1;
+    }
+
+    let tail = find_tail(start, &mut step, length);
+    Cycle { tail, length }
+}
+
+// Brent's algorithm, which needs fewer steps than Floyd's. Gives up with None if no state
+// repeats within `max_steps` steps.
+pub fn brent<S, F>(start: &S, mut step: F, max_steps: usize) -> Option<Cycle>
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut power = 1;
+    let mut length = 1;
+    let mut steps = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+    while tortoise != hare {
+        if steps >= max_steps {
+            return None;
+        }
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+        steps += 1;
+    }
+
+    let tail = find_tail(start, &mut step, length);
+    Some(Cycle { tail, length })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cycle::{brent, floyd, Cycle};
+    use std::collections::HashMap;
+
+    fn naive(start: u64, step: impl Fn(&u64) -> u64) -> Cycle {
+        let mut seen = HashMap::new();
+        let mut state = start;
+        let mut n = 0;
+        loop {
+            if let Some(first) = seen.insert(state, n) {
+                return Cycle {
+                    tail: first,
+                    length: n - first,
+                };
+            }
+            state = step(&state);
+            n += 1;
+        }
+    }
+
+    #[test]
+    fn agrees_with_naive_detection() {
+        for modulus in [7, 31, 255, 1000, 4093] {
+            let step = |x: &u64| (x * x + 1) % modulus;
+            for start in [0, 2, 5] {
+                let expected = naive(start, step);
+                assert_eq!(floyd(&start, step), expected);
+                assert_eq!(brent(&start, step, usize::MAX), Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn pure_cycle_has_no_tail() {
+        let cycle = floyd(&0, |x: &u32| (x + 1) % 12);
+        assert_eq!(cycle, Cycle { tail: 0, length: 12 });
+    }
+
+    #[test]
+    fn brent_gives_up() {
+        assert_eq!(brent(&0, |x: &u64| x + 1, 1000), None);
+    }
+
+    #[test]
+    fn reduces_past_the_tail() {
+        let step = |x: &u64| (x * x + 1) % 4093;
+        let cycle = floyd(&3, step);
+        let mut expected = 3;
+        for _ in 0..100_000 {
+            expected = step(&expected);
+        }
+        let mut state = 3;
+        for _ in 0..cycle.reduce(100_000) {
+            state = step(&state);
+        }
+        assert_eq!(state, expected);
+        assert_eq!(cycle.reduce(2), 2);
+    }
+}
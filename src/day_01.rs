@@ -1,17 +1,256 @@
+use crate::arith::{abs, add, mul, sub, sum};
+use crate::budget;
+use crate::error::AocError;
 use std::collections::HashMap;
+use std::io::BufRead;
+use std::iter::repeat_n;
 
-pub fn find_sorted_differences_sum(left: &mut [isize], right: &mut [isize]) -> isize {
+// Past this many ids between the smallest and largest, a tally switches from a count table to
+// a map
+const DENSE_SPAN: usize = 1 << 20;
+
+// Two columns of location ids, one pair per line
+pub fn parse_input(input: &str) -> Result<[Vec<isize>; 2], AocError> {
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let (l, r) = parse_line(line, index + 1)?;
+        left.push(l);
+        right.push(r);
+    }
+    Ok([left, right])
+}
+
+fn parse_line(line: &str, number: usize) -> Result<(isize, isize), AocError> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(l), Some(r), None) => Ok((l.parse()?, r.parse()?)),
+        (Some(_), None, None) => Err(AocError::parse(format!(
+            "line {} has only one location id, the lists differ in length",
+            number
+        ))),
+        _ => Err(AocError::parse(format!(
+            "expected two location ids on line {}",
+            number
+        ))),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reconciliation {
+    pub distance: isize,
+    pub similarity: isize,
+}
+
+// Both answers from a single pass over the lists, without holding them in memory. Only how
+// often each id turns up is kept, so memory grows with the ids rather than the number of lines.
+pub fn reconcile(mut reader: impl BufRead) -> Result<Reconciliation, AocError> {
+    let mut left = Tally::new();
+    let mut right = Tally::new();
+    let mut line = String::new();
+    let mut number = 0;
+    loop {
+        budget::step()?;
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        number += 1;
+        let (l, r) = parse_line(&line, number)?;
+        left.add(l);
+        right.add(r);
+    }
+    let left = left.into_sorted();
+    let right = right.into_sorted();
+    Ok(Reconciliation {
+        distance: paired_distance(&left, &right)?,
+        similarity: shared_similarity(&left, &right)?,
+    })
+}
+
+// How often each id turns up in one list. Ids close together are counted in a table indexed by
+// id, a counting sort, and ids spread wider than DENSE_SPAN go in a map whose keys are radix
+// sorted at the end.
+enum Tally {
+    Dense { first: isize, counts: Vec<u64> },
+    Sparse(HashMap<isize, u64>),
+}
+
+impl Tally {
+    fn new() -> Self {
+        Tally::Dense {
+            first: 0,
+            counts: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, id: isize) {
+        if let Tally::Dense { first, counts } = self {
+            if counts.is_empty() {
+                *first = id;
+                counts.push(0);
+            }
+            // i128 so the span of any two ids fits
+            let low = *first as i128;
+            let high = low + counts.len() as i128 - 1;
+            let wide_id = id as i128;
+            if wide_id < low || wide_id > high {
+                let needed = high.max(wide_id) - low.min(wide_id) + 1;
+                if needed > DENSE_SPAN as i128 {
+                    let sparse = Tally::Sparse(self.counts().collect());
+                    *self = sparse;
+                    return self.add(id);
+                }
+                // grow geometrically so ids arriving in descending order don't shift the table
+                // every time
+                let len = needed.max(2 * counts.len() as i128).min(DENSE_SPAN as i128);
+                if wide_id < low {
+                    let new_first = (high - len + 1).max(isize::MIN as i128);
+                    counts.splice(0..0, repeat_n(0, (low - new_first) as usize));
+                    *first = new_first as isize;
+                } else {
+                    let new_high = (low + len - 1).min(isize::MAX as i128);
+                    counts.resize((new_high - low + 1) as usize, 0);
+                }
+            }
+            counts[(wide_id - *first as i128) as usize] += 1;
+        } else if let Tally::Sparse(counts) = self {
+            *counts.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    fn counts(&self) -> Box<dyn Iterator<Item = (isize, u64)> + '_> {
+        match self {
+            Tally::Dense { first, counts } => Box::new(
+                counts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, count)| **count > 0)
+                    .map(|(offset, count)| ((*first as i128 + offset as i128) as isize, *count)),
+            ),
+            Tally::Sparse(counts) => Box::new(counts.iter().map(|(id, count)| (*id, *count))),
+        }
+    }
+
+    // Each id that turned up and how often, smallest id first
+    fn into_sorted(self) -> Vec<(isize, u64)> {
+        match self {
+            Tally::Dense { .. } => self.counts().collect(),
+            Tally::Sparse(counts) => {
+                let mut ids = counts.keys().copied().collect();
+                radix_sort(&mut ids);
+                ids.into_iter().map(|id| (id, counts[&id])).collect()
+            }
+        }
+    }
+}
+
+// LSD radix sort a byte at a time. Flipping the sign bit makes the unsigned order of the keys
+// match the signed order of the values.
+fn radix_sort(values: &mut Vec<isize>) {
+    let key = |value: isize| (value as usize) ^ (1 << (usize::BITS - 1));
+    let mut sorted = vec![0; values.len()];
+    for shift in (0..usize::BITS).step_by(8) {
+        let byte = |value: isize| (key(value) >> shift) & 0xff;
+        let mut starts = [0; 256];
+        for value in values.iter() {
+            starts[byte(*value)] += 1;
+        }
+        // every value has the same byte here, so this pass wouldn't move anything
+        if starts.contains(&values.len()) {
+            continue;
+        }
+        let mut start = 0;
+        for slot in starts.iter_mut() {
+            let count = *slot;
+            *slot = start;
+            start += count;
+        }
+        for value in values.iter() {
+            let slot = &mut starts[byte(*value)];
+            sorted[*slot] = *value;
+            *slot += 1;
+        }
+        std::mem::swap(values, &mut sorted);
+    }
+}
+
+fn count(count: u64) -> Result<isize, AocError> {
+    isize::try_from(count).map_err(|_| AocError::Overflow)
+}
+
+// The sorted difference sum worked out on counts: the smallest ids left on each side are paired
+// off as many times as both of them still have copies
+fn paired_distance(left: &[(isize, u64)], right: &[(isize, u64)]) -> Result<isize, AocError> {
+    let mut left = left.iter().copied();
+    let mut right = right.iter().copied();
+    let (mut l, mut r) = (left.next(), right.next());
+    let mut total = 0;
+    while let (Some((l_id, l_count)), Some((r_id, r_count))) = (l, r) {
+        budget::step()?;
+        let paired = l_count.min(r_count);
+        total = add(total, mul(abs(sub(l_id, r_id)?)?, count(paired)?)?)?;
+        l = if l_count == paired {
+            left.next()
+        } else {
+            Some((l_id, l_count - paired))
+        };
+        r = if r_count == paired {
+            right.next()
+        } else {
+            Some((r_id, r_count - paired))
+        };
+    }
+    if l.is_some() || r.is_some() {
+        return Err(AocError::unreachable("the lists differ in length"));
+    }
+    Ok(total)
+}
+
+// The similarity score worked out on counts: each id in both lists scores the id times how
+// often it's in the left times how often it's in the right
+fn shared_similarity(left: &[(isize, u64)], right: &[(isize, u64)]) -> Result<isize, AocError> {
+    let mut right = right.iter().peekable();
+    let mut total = 0;
+    for (id, l_count) in left {
+        budget::step()?;
+        while right.next_if(|(r_id, _)| r_id < id).is_some() {}
+        if let Some((_, r_count)) = right.next_if(|(r_id, _)| r_id == id) {
+            total = add(total, mul(mul(*id, count(*l_count)?)?, count(*r_count)?)?)?;
+        }
+    }
+    Ok(total)
+}
+
+pub fn find_sorted_differences_sum(
+    left: &mut [isize],
+    right: &mut [isize],
+) -> Result<isize, AocError> {
+    check_lengths(left, right)?;
     left.sort();
     right.sort();
-    left.iter().zip(right).map(|(l, r)| (*l - *r).abs()).sum()
+    let differences = left
+        .iter()
+        .zip(right)
+        .map(|(l, r)| {
+            budget::step()?;
+            abs(sub(*l, *r)?)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    sum(differences)
 }
 
-pub fn get_similarity_score(left: &[isize], right: &[isize]) -> isize {
+pub fn get_similarity_score(left: &[isize], right: &[isize]) -> Result<isize, AocError> {
     // create a list of the counts of each value to avoid running through the list alot
     let count_lookup = create_count_lookup(right);
-    left.iter()
-        .map(|val| *val * *count_lookup.get(val).unwrap_or(&0))
-        .sum()
+    let scores = left
+        .iter()
+        .map(|val| {
+            budget::step()?;
+            mul(*val, *count_lookup.get(val).unwrap_or(&0))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    sum(scores)
 }
 
 fn create_count_lookup(values: &[isize]) -> HashMap<isize, isize> {
@@ -24,41 +263,371 @@ fn create_count_lookup(values: &[isize]) -> HashMap<isize, isize> {
     count_lookup
 }
 
+// How the two lists compare beyond distance and similarity. Every list of (id, count) pairs
+// is sorted by id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListComparison {
+    // ids in both lists, as many times as the list with fewer copies has them
+    pub intersection: Vec<(isize, isize)>,
+    // ids the left list has more copies of than the right, and how many more
+    pub left_difference: Vec<(isize, isize)>,
+    // ids the right list has more copies of than the left, and how many more
+    pub right_difference: Vec<(isize, isize)>,
+    // left count minus right count for every id whose counts differ
+    pub count_deltas: Vec<(isize, isize)>,
+    // rank correlations between the columns line by line, None when a column has fewer than
+    // two distinct ids
+    pub spearman: Option<f64>,
+    pub kendall_tau: Option<f64>,
+}
+
+impl ListComparison {
+    pub fn new(left: &[isize], right: &[isize]) -> Result<Self, AocError> {
+        check_lengths(left, right)?;
+        let left_counts = create_count_lookup(left);
+        let right_counts = create_count_lookup(right);
+        let mut ids = left_counts
+            .keys()
+            .chain(right_counts.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        let mut comparison = ListComparison {
+            intersection: Vec::new(),
+            left_difference: Vec::new(),
+            right_difference: Vec::new(),
+            count_deltas: Vec::new(),
+            spearman: spearman(left, right),
+            kendall_tau: kendall_tau(left, right),
+        };
+        for id in ids {
+            budget::step()?;
+            let l = *left_counts.get(&id).unwrap_or(&0);
+            let r = *right_counts.get(&id).unwrap_or(&0);
+            if l.min(r) > 0 {
+                comparison.intersection.push((id, l.min(r)));
+            }
+            if l > r {
+                comparison.left_difference.push((id, l - r));
+            } else if r > l {
+                comparison.right_difference.push((id, r - l));
+            }
+            if l != r {
+                comparison.count_deltas.push((id, l - r));
+            }
+        }
+        Ok(comparison)
+    }
+
+    // The k ids whose counts differ the most between the lists, biggest difference first and
+    // smaller ids first on a tie
+    pub fn most_divergent(&self, k: usize) -> Vec<(isize, isize)> {
+        let mut deltas = self.count_deltas.clone();
+        deltas.sort_by_key(|(id, delta)| (std::cmp::Reverse(delta.unsigned_abs()), *id));
+        deltas.truncate(k);
+        deltas
+    }
+}
+
+fn check_lengths(left: &[isize], right: &[isize]) -> Result<(), AocError> {
+    if left.len() != right.len() {
+        return Err(AocError::domain(format!(
+            "the left list has {} location ids and the right list has {}",
+            left.len(),
+            right.len()
+        )));
+    }
+    Ok(())
+}
+
+// 1-based ranks, ids that tie share the average of their ranks
+fn ranks(values: &[isize]) -> Vec<f64> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| values[i]);
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    for tied in order.chunk_by(|&a, &b| values[a] == values[b]) {
+        let rank = (2 * start + tied.len() + 1) as f64 / 2.0;
+        for &i in tied {
+            ranks[i] = rank;
+        }
+        start += tied.len();
+    }
+    ranks
+}
+
+// Pearson correlation of the ranks
+fn spearman(left: &[isize], right: &[isize]) -> Option<f64> {
+    let (left, right) = (ranks(left), ranks(right));
+    let n = left.len() as f64;
+    let mean = (n + 1.0) / 2.0;
+    let mut covariance = 0.0;
+    let (mut left_variance, mut right_variance) = (0.0, 0.0);
+    for (l, r) in left.iter().zip(&right) {
+        covariance += (l - mean) * (r - mean);
+        left_variance += (l - mean) * (l - mean);
+        right_variance += (r - mean) * (r - mean);
+    }
+    if left_variance == 0.0 || right_variance == 0.0 {
+        return None;
+    }
+    Some(covariance / (left_variance * right_variance).sqrt())
+}
+
+// Kendall's tau-b, which allows for ties, in O(n log n) with Knight's algorithm: sort the pairs
+// by left id, then count how many swaps a merge sort on the right ids needs. Each swap is a
+// discordant pair.
+fn kendall_tau(left: &[isize], right: &[isize]) -> Option<f64> {
+    let mut pairs = left
+        .iter()
+        .copied()
+        .zip(right.iter().copied())
+        .collect::<Vec<_>>();
+    pairs.sort();
+    let all_pairs = tied_pairs(&[pairs.len()]);
+    let left_ties = tied_pairs(&run_lengths(&pairs, |(l, _)| *l));
+    let both_ties = tied_pairs(&run_lengths(&pairs, |pair| *pair));
+    let mut right = pairs.into_iter().map(|(_, r)| r).collect::<Vec<_>>();
+    let swaps = merge_sort_swaps(&mut right);
+    let right_ties = tied_pairs(&run_lengths(&right, |r| *r));
+
+    let left_pairs = (all_pairs - left_ties) as f64;
+    let right_pairs = (all_pairs - right_ties) as f64;
+    if left_pairs == 0.0 || right_pairs == 0.0 {
+        return None;
+    }
+    // concordant minus discordant pairs, counting only pairs tied on neither side
+    let score = (all_pairs + both_ties) as f64 - (left_ties + right_ties + 2 * swaps) as f64;
+    Some(score / (left_pairs * right_pairs).sqrt())
+}
+
+fn run_lengths<T, K: PartialEq>(sorted: &[T], key: impl Fn(&T) -> K) -> Vec<usize> {
+    sorted
+        .chunk_by(|a, b| key(a) == key(b))
+        .map(|run| run.len())
+        .collect()
+}
+
+// How many pairs can be made within each run
+fn tied_pairs(run_lengths: &[usize]) -> u64 {
+    run_lengths
+        .iter()
+        .map(|&t| (t as u64) * (t as u64).saturating_sub(1) / 2)
+        .sum()
+}
+
+// Sorts the values and returns how many pairs were out of order
+fn merge_sort_swaps(values: &mut [isize]) -> u64 {
+    if values.len() < 2 {
+        return 0;
+    }
+    let middle = values.len() / 2;
+    let mut swaps =
+        merge_sort_swaps(&mut values[..middle]) + merge_sort_swaps(&mut values[middle..]);
+    let mut merged = Vec::with_capacity(values.len());
+    let (mut i, mut j) = (0, middle);
+    while i < middle && j < values.len() {
+        if values[i] <= values[j] {
+            merged.push(values[i]);
+            i += 1;
+        } else {
+            merged.push(values[j]);
+            swaps += (middle - i) as u64;
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&values[i..middle]);
+    merged.extend_from_slice(&values[j..]);
+    values.copy_from_slice(&merged);
+    swaps
+}
+
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_01_EASY, DAY_01_HARD};
-    use crate::day_01::{create_count_lookup, find_sorted_differences_sum, get_similarity_score};
+    use crate::day_01::{
+        create_count_lookup, find_sorted_differences_sum, get_similarity_score, parse_input,
+        radix_sort, reconcile, ListComparison, Reconciliation,
+    };
+    use crate::error::AocError;
+    use crate::generators::{self, Rng};
 
     const INPUT: &str = include_str!("../resources/day_01/easy.txt");
 
-    fn parse_input(input: &str) -> [Vec<isize>; 2] {
-        let mut left = Vec::new();
-        let mut right = Vec::new();
-        input.split('\n').for_each(|line| {
-            let mut parts = line.split("   ").map(|val| val.parse::<isize>().unwrap());
-            left.push(parts.next().unwrap());
-            right.push(parts.next().unwrap());
-        });
-        [left, right]
-    }
-
     #[test]
     fn easy() {
-        let mut lists = parse_input(INPUT);
+        let lists = parse_input(INPUT).unwrap();
         let left = &mut lists[0].clone();
         let right = &mut lists[1].clone();
-        let left = find_sorted_differences_sum(left, right);
+        let left = find_sorted_differences_sum(left, right).unwrap();
         let right = DAY_01_EASY;
         assert_eq!(left, right);
     }
 
     #[test]
     fn hard() {
-        let mut lists = parse_input(INPUT);
+        let lists = parse_input(INPUT).unwrap();
         let left = &mut lists[0].clone();
         let right = &mut lists[1].clone();
-        let left = get_similarity_score(left, right);
+        let left = get_similarity_score(left, right).unwrap();
         let right = DAY_01_HARD;
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn streamed() {
+        let left = reconcile(INPUT.as_bytes()).unwrap();
+        let right = Reconciliation {
+            distance: DAY_01_EASY,
+            similarity: DAY_01_HARD,
+        };
+        assert_eq!(left, right);
+    }
+
+    // Lists with ids in a narrow band, in descending order and spread too wide for a count table
+    fn location_lists(seed: u64) -> String {
+        let mut rng = Rng::new(seed);
+        let size = 1 + rng.below(200);
+        match seed % 3 {
+            0 => generators::location_lists(seed, size),
+            1 => (0..size)
+                .map(|i| format!("{} {}", 1000 - i as isize, rng.between(-5, 5)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => (0..size)
+                .map(|_| {
+                    let spread = *rng.choose(&[10, 1 << 20, 1 << 40]);
+                    format!(
+                        "{} {}",
+                        rng.between(-spread, spread),
+                        rng.between(-spread, spread)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    #[test]
+    fn streamed_matches_in_memory() {
+        for seed in 0..300 {
+            let input = location_lists(seed);
+            let [mut left, mut right] = parse_input(&input).unwrap();
+            let similarity = get_similarity_score(&left, &right).unwrap();
+            let distance = find_sorted_differences_sum(&mut left, &mut right).unwrap();
+            let right = Reconciliation {
+                distance,
+                similarity,
+            };
+            assert_eq!(reconcile(input.as_bytes()).unwrap(), right, "{}", input);
+        }
+    }
+
+    #[test]
+    fn mismatched_lengths() {
+        let right = Err(AocError::parse(
+            "line 2 has only one location id, the lists differ in length",
+        ));
+        assert_eq!(reconcile("1 2\n3\n4 5".as_bytes()), right);
+        assert_eq!(parse_input("1 2\n3\n4 5"), right.map(|_| [vec![], vec![]]));
+        let right = Err(AocError::domain(
+            "the left list has 2 location ids and the right list has 1",
+        ));
+        assert_eq!(find_sorted_differences_sum(&mut [1, 2], &mut [1]), right);
+    }
+
+    #[test]
+    fn sorts_any_ids() {
+        let mut rng = Rng::new(1);
+        let mut values = (0..1000)
+            .map(|_| rng.next_u64() as isize >> rng.below(64))
+            .chain([isize::MIN, isize::MAX, 0, -1])
+            .collect::<Vec<_>>();
+        let mut right = values.clone();
+        right.sort();
+        radix_sort(&mut values);
+        assert_eq!(values, right);
+    }
+
+    #[test]
+    fn compares_lists() {
+        let left = [3, 4, 2, 1, 3, 3, 7, 7, 7];
+        let right = [4, 3, 5, 3, 9, 3, 4, 4, 1];
+        let comparison = ListComparison::new(&left, &right).unwrap();
+        assert_eq!(comparison.intersection, [(1, 1), (3, 3), (4, 1)]);
+        assert_eq!(comparison.left_difference, [(2, 1), (7, 3)]);
+        assert_eq!(comparison.right_difference, [(4, 2), (5, 1), (9, 1)]);
+        assert_eq!(
+            comparison.count_deltas,
+            [(2, 1), (4, -2), (5, -1), (7, 3), (9, -1)]
+        );
+        assert_eq!(comparison.most_divergent(3), [(7, 3), (4, -2), (2, 1)]);
+        assert_eq!(comparison.most_divergent(10).len(), 5);
+        assert_eq!(
+            ListComparison::new(&left, &right[1..]),
+            Err(AocError::domain(
+                "the left list has 9 location ids and the right list has 8"
+            ))
+        );
+    }
+
+    #[test]
+    fn rank_correlations() {
+        let correlations = |left: &[isize], right: &[isize]| {
+            let comparison = ListComparison::new(left, right).unwrap();
+            (comparison.spearman, comparison.kendall_tau)
+        };
+        assert_eq!(
+            correlations(&[1, 2, 3, 4], &[10, 20, 30, 40]),
+            (Some(1.0), Some(1.0))
+        );
+        assert_eq!(
+            correlations(&[1, 2, 3, 4], &[9, 7, 5, 3]),
+            (Some(-1.0), Some(-1.0))
+        );
+        assert_eq!(correlations(&[1, 2, 3], &[5, 5, 5]), (None, None));
+        assert_eq!(correlations(&[], &[]), (None, None));
+        // ties on both sides, worked out by hand: ranks 1.5 1.5 3 4 5 and 1 2 3.5 3.5 5
+        let (spearman, kendall_tau) = correlations(&[1, 1, 2, 3, 4], &[1, 2, 3, 3, 5]);
+        assert!((spearman.unwrap() - 9.0 / 9.5).abs() < 1e-12);
+        assert!((kendall_tau.unwrap() - 8.0 / 9.0).abs() < 1e-12);
+    }
+
+    // Kendall's tau-b the slow way, looking at every pair of lines
+    fn reference_kendall_tau(left: &[isize], right: &[isize]) -> Option<f64> {
+        let (mut score, mut left_pairs, mut right_pairs) = (0.0, 0.0, 0.0);
+        for i in 0..left.len() {
+            for j in i + 1..left.len() {
+                let l = (left[i] - left[j]).signum();
+                let r = (right[i] - right[j]).signum();
+                score += (l * r) as f64;
+                left_pairs += (l != 0) as u8 as f64;
+                right_pairs += (r != 0) as u8 as f64;
+            }
+        }
+        (left_pairs > 0.0 && right_pairs > 0.0).then(|| score / (left_pairs * right_pairs).sqrt())
+    }
+
+    #[test]
+    fn kendall_tau_matches_reference() {
+        for seed in 0..200 {
+            let mut rng = Rng::new(seed);
+            let size = rng.below(40);
+            let spread = rng.between(1, 20);
+            let mut column = || {
+                (0..size)
+                    .map(|_| rng.between(0, spread))
+                    .collect::<Vec<_>>()
+            };
+            let (left, right) = (column(), column());
+            let left_tau = ListComparison::new(&left, &right).unwrap().kendall_tau;
+            let right_tau = reference_kendall_tau(&left, &right);
+            match (left_tau, right_tau) {
+                (Some(l), Some(r)) => assert!((l - r).abs() < 1e-9, "{:?} {:?}", left, right),
+                (l, r) => assert_eq!(l, r),
+            }
+        }
+    }
 }
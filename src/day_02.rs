@@ -1,98 +1,422 @@
+use crate::budget;
+use crate::error::AocError;
+use crate::parallel::Parallelism;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
 type Report = Vec<isize>;
 
-pub fn find_number_of_safe_reports(reports: &[Report]) -> isize {
-    reports
-        .iter()
-        .map(|report| is_safe_report(report) as isize)
-        .sum()
+// One report of whitespace separated levels per line
+pub fn parse_input(input: &str) -> Result<Vec<Report>, AocError> {
+    input
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|level| Ok(level.parse()?))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn find_number_of_safe_reports(reports: &[Report]) -> Result<isize, AocError> {
+    find_number_of_safe_reports_with(reports, Parallelism::sequential())
+}
+
+pub fn find_number_of_safe_reports_with(
+    reports: &[Report],
+    parallelism: Parallelism,
+) -> Result<isize, AocError> {
+    count_safe_reports(reports, &SAFE, parallelism)
+}
+
+fn count_safe(verdicts: Vec<Result<bool, AocError>>) -> Result<isize, AocError> {
+    verdicts
+        .into_iter()
+        .try_fold(0, |count, safe| Ok(count + safe? as isize))
+}
+
+// What makes a report safe: every step between kept levels is within `steps` (as an absolute
+// difference), the levels only go one way if `monotonic`, and up to `removals` levels may be
+// dropped to get there
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReactorRules {
+    pub steps: Range<isize>,
+    pub monotonic: bool,
+    pub removals: usize,
 }
 
-fn is_safe_report(report: &Report) -> bool {
-    let mut index = 1;
-    let mut order = 0;
+pub const SAFE: ReactorRules = ReactorRules {
+    steps: 1..4,
+    monotonic: true,
+    removals: 0,
+};
+
+// The Problem Dampener lets a single bad level go
+pub const DAMPENED: ReactorRules = ReactorRules {
+    removals: 1,
+    ..SAFE
+};
 
-    while index < report.len() {
-        let last_level = report.get(index - 1).unwrap();
-        let current_level = report.get(index).unwrap();
-        let diff = current_level.checked_sub(*last_level).unwrap();
-        let abs_diff = diff.abs();
+// Which way the kept levels have gone so far. Flat until the first step up or down, and for
+// rules that aren't monotonic it stays Flat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Trend {
+    Flat,
+    Up,
+    Down,
+}
+
+const TRENDS: [Trend; 3] = [Trend::Flat, Trend::Up, Trend::Down];
 
-        if !(1..4).contains(&abs_diff) {
-            return false;
+impl ReactorRules {
+    // The trend after a step of `difference`, or None if the step breaks monotonicity
+    fn follow(&self, trend: Trend, difference: isize) -> Option<Trend> {
+        if !self.monotonic {
+            return Some(Trend::Flat);
         }
+        match (trend, difference.signum()) {
+            (trend, 0) => Some(trend),
+            (Trend::Flat | Trend::Up, 1) => Some(Trend::Up),
+            (Trend::Flat | Trend::Down, -1) => Some(Trend::Down),
+            _ => None,
+        }
+    }
+}
 
-        let current_order = diff / abs_diff;
+pub fn count_safe_reports(
+    reports: &[Report],
+    rules: &ReactorRules,
+    parallelism: Parallelism,
+) -> Result<isize, AocError> {
+    count_safe(parallelism.map(reports, |report| is_safe(report, rules)))
+}
 
-        if order == 0 {
-            order = current_order
-        } else if order != current_order {
-            return false;
+// O(n·k) for k removals. For each level and trend this keeps the fewest removals that leave
+// that level as the last one kept, since fewer removals never rules out anything more removals
+// would allow. The level kept before it is at most k + 1 back.
+pub fn is_safe(report: &[isize], rules: &ReactorRules) -> Result<bool, AocError> {
+    let length = report.len();
+    let removals = rules.removals;
+    if length <= removals + 1 {
+        return Ok(true);
+    }
+    let mut fewest = vec![[None::<usize>; 3]; length];
+    for index in 0..length {
+        budget::step()?;
+        // everything before it removed
+        if index <= removals {
+            fewest[index][Trend::Flat as usize] = Some(index);
+        }
+        for previous in index.saturating_sub(removals + 1)..index {
+            let gap = index - previous - 1;
+            let difference = report[index]
+                .checked_sub(report[previous])
+                .ok_or(AocError::Overflow)?;
+            let step = difference.checked_abs().ok_or(AocError::Overflow)?;
+            if !rules.steps.contains(&step) {
+                continue;
+            }
+            for trend in TRENDS {
+                let Some(removed) = fewest[previous][trend as usize] else {
+                    continue;
+                };
+                let Some(next) = rules.follow(trend, difference) else {
+                    continue;
+                };
+                let removed = removed + gap;
+                let best = &mut fewest[index][next as usize];
+                if removed <= removals && best.is_none_or(|best| removed < best) {
+                    *best = Some(removed);
+                }
+            }
         }
+    }
+    // everything after the last level kept removed too
+    Ok((length - removals - 1..length).any(|last| {
+        fewest[last]
+            .iter()
+            .flatten()
+            .any(|removed| removed + (length - 1 - last) <= removals)
+    }))
+}
+
+pub fn find_number_of_dampened_safe_reports(reports: &[Report]) -> Result<isize, AocError> {
+    find_number_of_dampened_safe_reports_with(reports, Parallelism::sequential())
+}
 
-        index += 1;
+pub fn find_number_of_dampened_safe_reports_with(
+    reports: &[Report],
+    parallelism: Parallelism,
+) -> Result<isize, AocError> {
+    count_safe_reports(reports, &DAMPENED, parallelism)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Violation {
+    StepTooLarge,
+    // only when the rules' steps start above 1
+    StepTooSmall,
+    ZeroStep,
+    DirectionChange,
+}
+
+// Why a report is unsafe under a set of rules, ignoring how many removals they allow
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnosis {
+    // the level where the report first breaks the rules and how, None if it's safe as it is
+    pub violation: Option<(usize, Violation)>,
+    // each level that could be removed on its own to make the report safe, empty if it's
+    // already safe
+    pub fixes: Vec<usize>,
+}
+
+pub fn diagnose(report: &[isize], rules: &ReactorRules) -> Result<Diagnosis, AocError> {
+    let levels = || report.iter().copied().enumerate();
+    let violation = first_violation(levels(), rules)?;
+    let mut fixes = Vec::new();
+    if violation.is_some() {
+        for removed in 0..report.len() {
+            let kept = levels().filter(|(index, _)| *index != removed);
+            if first_violation(kept, rules)?.is_none() {
+                fixes.push(removed);
+            }
+        }
     }
-    true
+    Ok(Diagnosis { violation, fixes })
 }
 
-pub fn find_number_of_dampened_safe_reports(reports: &[Report]) -> isize {
-    reports
-        .iter()
-        .map(|report| {
-            let sub_reports = create_sub_reports(report);
-            sub_reports.iter().any(is_safe_report) as isize
-        })
-        .sum()
+fn first_violation(
+    levels: impl Iterator<Item = (usize, isize)>,
+    rules: &ReactorRules,
+) -> Result<Option<(usize, Violation)>, AocError> {
+    let mut trend = Trend::Flat;
+    let mut previous = None;
+    for (index, level) in levels {
+        let Some(last) = previous.replace(level) else {
+            continue;
+        };
+        let difference = level.checked_sub(last).ok_or(AocError::Overflow)?;
+        let step = difference.checked_abs().ok_or(AocError::Overflow)?;
+        if !rules.steps.contains(&step) {
+            let violation = if step == 0 {
+                Violation::ZeroStep
+            } else if step < rules.steps.start {
+                Violation::StepTooSmall
+            } else {
+                Violation::StepTooLarge
+            };
+            return Ok(Some((index, violation)));
+        }
+        match rules.follow(trend, difference) {
+            Some(next) => trend = next,
+            None => return Ok(Some((index, Violation::DirectionChange))),
+        }
+    }
+    Ok(None)
 }
 
-fn create_sub_reports(report: &Report) -> Vec<Report> {
-    (0..report.len())
-        .map(|skipped| create_sub_report(skipped, report))
-        .collect()
+// How a whole list of reports fares, mostly for checking against the puzzle's examples
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SafetySummary {
+    pub safe: usize,
+    // unsafe, but removing one level would fix them
+    pub fixable: usize,
+    pub unfixable: usize,
+    // how many unsafe reports break the rules first in each way
+    pub violations: BTreeMap<Violation, usize>,
 }
 
-fn create_sub_report(skipped: usize, report: &Report) -> Report {
-    let mut sub_report = Vec::new();
-    report.iter().enumerate().for_each(|(index, value)| {
-        if index != skipped {
-            sub_report.push(*value);
+pub fn summarise(reports: &[Report], rules: &ReactorRules) -> Result<SafetySummary, AocError> {
+    let mut summary = SafetySummary::default();
+    for report in reports {
+        let diagnosis = diagnose(report, rules)?;
+        match diagnosis.violation {
+            None => summary.safe += 1,
+            Some((_, violation)) => {
+                *summary.violations.entry(violation).or_insert(0) += 1;
+                if diagnosis.fixes.is_empty() {
+                    summary.unfixable += 1;
+                } else {
+                    summary.fixable += 1;
+                }
+            }
         }
-    });
-    sub_report
+    }
+    Ok(summary)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_02_EASY, DAY_02_HARD};
     use crate::day_02::{
-        find_number_of_dampened_safe_reports, find_number_of_safe_reports, Report,
+        diagnose, find_number_of_dampened_safe_reports, find_number_of_dampened_safe_reports_with,
+        find_number_of_safe_reports, find_number_of_safe_reports_with, is_safe, parse_input,
+        summarise, Diagnosis, ReactorRules, SafetySummary, Violation, DAMPENED, SAFE,
     };
+    use crate::generators::{self, Rng};
+    use crate::parallel::Parallelism;
+    use std::collections::BTreeMap;
 
     const INPUT: &str = include_str!("../resources/day_02/easy.txt");
+    const EXAMPLE: &str = "7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9";
 
-    fn parse_input(input: &str) -> Vec<Report> {
-        input
-            .split('\n')
-            .map(|line| {
-                line.split_whitespace()
-                    .map(|val| val.parse().unwrap())
-                    .collect()
-            })
-            .collect()
-    }
     #[test]
     fn easy() {
-        let reports = parse_input(INPUT);
-        let left = find_number_of_safe_reports(&reports);
+        let reports = parse_input(INPUT).unwrap();
+        let left = find_number_of_safe_reports(&reports).unwrap();
         let right = DAY_02_EASY;
         assert_eq!(left, right);
     }
 
     #[test]
     fn hard() {
-        let reports = parse_input(INPUT);
-        let left = find_number_of_dampened_safe_reports(&reports);
+        let reports = parse_input(INPUT).unwrap();
+        let left = find_number_of_dampened_safe_reports(&reports).unwrap();
 
         let right = DAY_02_HARD;
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut inputs = vec![INPUT.to_string()];
+        inputs.extend((0..20).map(|seed| generators::level_reports(seed, 50)));
+        for input in inputs {
+            let reports = parse_input(&input).unwrap();
+            for workers in [2, 3, 8] {
+                let parallelism = Parallelism::with_workers(workers);
+                assert_eq!(
+                    find_number_of_safe_reports_with(&reports, parallelism),
+                    find_number_of_safe_reports(&reports)
+                );
+                assert_eq!(
+                    find_number_of_dampened_safe_reports_with(&reports, parallelism),
+                    find_number_of_dampened_safe_reports(&reports)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn configurable_rules() {
+        let report = [1, 2, 9, 3, 4, 20, 5];
+        assert!(!is_safe(&report, &DAMPENED).unwrap());
+        let two_removals = ReactorRules {
+            removals: 2,
+            ..DAMPENED
+        };
+        assert!(is_safe(&report, &two_removals).unwrap());
+
+        let zig_zag = [1, 3, 2, 4, 4];
+        assert!(!is_safe(&zig_zag, &SAFE).unwrap());
+        let any_direction = ReactorRules {
+            steps: 0..3,
+            monotonic: false,
+            ..SAFE
+        };
+        assert!(is_safe(&zig_zag, &any_direction).unwrap());
+        let flat_steps = ReactorRules {
+            steps: 0..3,
+            ..DAMPENED
+        };
+        assert!(is_safe(&zig_zag, &flat_steps).unwrap());
+    }
+
+    // Tries every way of removing up to `removals` levels and checks what's left pair by pair
+    fn reference_is_safe(report: &[isize], rules: &ReactorRules) -> bool {
+        let kept_is_safe = |kept: &[isize]| {
+            let differences = kept
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .collect::<Vec<_>>();
+            differences
+                .iter()
+                .all(|difference| rules.steps.contains(&difference.abs()))
+                && (!rules.monotonic
+                    || differences.iter().all(|difference| *difference >= 0)
+                    || differences.iter().all(|difference| *difference <= 0))
+        };
+        (0..1u32 << report.len())
+            .filter(|removed| removed.count_ones() as usize <= rules.removals)
+            .any(|removed| {
+                let kept = (0..report.len())
+                    .filter(|index| removed & (1 << index) == 0)
+                    .map(|index| report[index])
+                    .collect::<Vec<_>>();
+                kept_is_safe(&kept)
+            })
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..500 {
+            let mut rng = Rng::new(seed);
+            let rules = ReactorRules {
+                steps: rng.between(0, 2)..rng.between(1, 5),
+                monotonic: !rng.one_in(3),
+                removals: rng.below(4),
+            };
+            let input = generators::level_reports(seed, 20);
+            for report in parse_input(&input).unwrap() {
+                assert_eq!(
+                    is_safe(&report, &rules).unwrap(),
+                    reference_is_safe(&report, &rules),
+                    "{:?} {:?}",
+                    report,
+                    rules
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn diagnoses_example() {
+        let reports = parse_input(EXAMPLE).unwrap();
+        let diagnoses = reports
+            .iter()
+            .map(|report| diagnose(report, &SAFE).unwrap())
+            .collect::<Vec<_>>();
+        let diagnosis = |violation, fixes: &[usize]| Diagnosis {
+            violation,
+            fixes: fixes.to_vec(),
+        };
+        let right = [
+            diagnosis(None, &[]),
+            diagnosis(Some((2, Violation::StepTooLarge)), &[]),
+            diagnosis(Some((3, Violation::StepTooLarge)), &[]),
+            diagnosis(Some((2, Violation::DirectionChange)), &[1, 2]),
+            diagnosis(Some((3, Violation::ZeroStep)), &[2, 3]),
+            diagnosis(None, &[]),
+        ];
+        assert_eq!(diagnoses, right);
+
+        let right = SafetySummary {
+            safe: 2,
+            fixable: 2,
+            unfixable: 2,
+            violations: BTreeMap::from([
+                (Violation::StepTooLarge, 2),
+                (Violation::ZeroStep, 1),
+                (Violation::DirectionChange, 1),
+            ]),
+        };
+        assert_eq!(summarise(&reports, &SAFE).unwrap(), right);
+
+        let wide_steps = ReactorRules {
+            steps: 2..4,
+            ..SAFE
+        };
+        let left = diagnose(&reports[0], &wide_steps).unwrap();
+        assert_eq!(left.violation, Some((1, Violation::StepTooSmall)));
+    }
+
+    #[test]
+    fn summary_matches_answers() {
+        let reports = parse_input(INPUT).unwrap();
+        let summary = summarise(&reports, &SAFE).unwrap();
+        assert_eq!(summary.safe as isize, DAY_02_EASY);
+        assert_eq!((summary.safe + summary.fixable) as isize, DAY_02_HARD);
+        let unsafe_reports = summary.violations.values().sum::<usize>();
+        assert_eq!(unsafe_reports, summary.fixable + summary.unfixable);
+    }
 }
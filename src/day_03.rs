@@ -1,78 +1,813 @@
-use lazy_static::lazy_static;
-use regex::Regex;
+use crate::arith::{add, mul};
+use crate::budget;
+use crate::error::AocError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::ops::Range;
 
-pub fn get_computer_instructions_sum(instructions: &str) -> usize {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
-    };
+// An operation the lexer looks for, written as its name followed by `arity` comma separated
+// operands of one to three digits in brackets, e.g. mul(2,4)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Signature {
+    pub name: &'static str,
+    pub arity: usize,
+}
 
-    RE.captures_iter(instructions)
-        .map(|cap| {
-            let first: usize = cap.get(1).unwrap().as_str().parse().unwrap();
-            let second: usize = cap.get(2).unwrap().as_str().parse().unwrap();
-            first * second
-        })
-        .sum()
+pub const MUL: Signature = Signature {
+    name: "mul",
+    arity: 2,
+};
+pub const DO: Signature = Signature {
+    name: "do",
+    arity: 0,
+};
+pub const DONT: Signature = Signature {
+    name: "don't",
+    arity: 0,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Mul(usize, usize),
+    Do,
+    Dont,
+    // any other operation the lexer was given a signature for
+    Op(&'static str, Vec<usize>),
+}
+
+impl Instruction {
+    fn new(signature: Signature, operands: Vec<usize>) -> Self {
+        match (signature.name, operands.as_slice()) {
+            ("mul", &[a, b]) => Instruction::Mul(a, b),
+            ("do", []) => Instruction::Do,
+            ("don't", []) => Instruction::Dont,
+            _ => Instruction::Op(signature.name, operands),
+        }
+    }
+
+    pub fn signature(&self) -> Signature {
+        match self {
+            Instruction::Mul(..) => MUL,
+            Instruction::Do => DO,
+            Instruction::Dont => DONT,
+            Instruction::Op(name, operands) => Signature {
+                name,
+                arity: operands.len(),
+            },
+        }
+    }
 }
 
-enum Toggle {
-    On,
-    Off,
+// An instruction and the bytes of memory it was read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub instruction: Instruction,
+    pub span: Range<usize>,
 }
 
-impl From<&str> for Toggle {
-    fn from(value: &str) -> Self {
-        match value {
-            "do" => Toggle::On,
-            "don't" => Toggle::Off,
-            _ => panic!(),
+// Scans corrupted memory once, front to back, for well formed instructions. Anything that
+// doesn't complete one is skipped a byte at a time, so `mul(mul(2,3)` still finds mul(2,3).
+pub struct Lexer<'a> {
+    memory: &'a [u8],
+    position: usize,
+    signatures: Vec<Signature>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(memory: &'a str) -> Self {
+        Self::with_signatures(memory, &[MUL, DO, DONT])
+    }
+
+    pub fn with_signatures(memory: &'a str, signatures: &[Signature]) -> Self {
+        Lexer {
+            memory: memory.as_bytes(),
+            position: 0,
+            signatures: signatures.to_vec(),
         }
     }
 }
-pub fn get_toggled_computer_instructions_sum(instructions: &str) -> usize {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"(?P<toggle>don't|do)|mul\((?P<first>\d{1,3}),(?P<second>\d{1,3})\)")
-                .unwrap();
-    };
 
-    let mut toggle = Toggle::On;
-    let mut total = 0;
-
-    RE.captures_iter(instructions).for_each(|cap| {
-        let toggle_match = cap.name("toggle");
-        match toggle_match {
-            None => {
-                if let Toggle::On = toggle {
-                    let first: usize = cap.name("first").unwrap().as_str().parse().unwrap();
-                    let second: usize = cap.name("second").unwrap().as_str().parse().unwrap();
-                    total += first * second;
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.memory.len() {
+            let start = self.position;
+            if let Some((instruction, end)) = token_at(self.memory, start, &self.signatures) {
+                self.position = end;
+                return Some(Token {
+                    instruction,
+                    span: start..end,
+                });
+            }
+            self.position += 1;
+        }
+        None
+    }
+}
+
+// The instruction starting at `start` and where it ends. Never looks further ahead than
+// longest_call of the signatures.
+fn token_at(memory: &[u8], start: usize, signatures: &[Signature]) -> Option<(Instruction, usize)> {
+    signatures.iter().find_map(|signature| {
+        let (operands, end) = call_at(memory, start, *signature)?;
+        Some((Instruction::new(*signature, operands), end))
+    })
+}
+
+fn call_at(memory: &[u8], start: usize, signature: Signature) -> Option<(Vec<usize>, usize)> {
+    if !memory[start..].starts_with(signature.name.as_bytes()) {
+        return None;
+    }
+    let mut position = start + signature.name.len();
+    if memory.get(position) != Some(&b'(') {
+        return None;
+    }
+    position += 1;
+    let mut operands = Vec::with_capacity(signature.arity);
+    for index in 0..signature.arity {
+        if index > 0 {
+            if memory.get(position) != Some(&b',') {
+                return None;
+            }
+            position += 1;
+        }
+        let digits = memory[position..]
+            .iter()
+            .take(4)
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if !(1..=3).contains(&digits) {
+            return None;
+        }
+        let operand = memory[position..position + digits]
+            .iter()
+            .fold(0, |operand, digit| operand * 10 + (digit - b'0') as usize);
+        operands.push(operand);
+        position += digits;
+    }
+    if memory.get(position) != Some(&b')') {
+        return None;
+    }
+    Some((operands, position + 1))
+}
+
+// The most bytes an instruction with one of these signatures can take up: the name, brackets,
+// three digits per operand and the commas between them
+fn longest_call(signatures: &[Signature]) -> usize {
+    signatures
+        .iter()
+        .map(|signature| signature.name.len() + 2 + (4 * signature.arity).saturating_sub(1))
+        .max()
+        .unwrap_or(0)
+}
+
+pub const CHUNK: usize = 64 * 1024;
+
+// Lexes memory read a chunk at a time, so it needs no more than a chunk plus one instruction's
+// worth of memory however big the dump is. A position is only looked at once everything an
+// instruction there could cover has been read, which is what keeps `mu|l(12,3)` or `don|'t()`
+// split across two chunks from being missed. Spans count bytes from the start of the stream.
+pub struct StreamLexer<R> {
+    reader: R,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    position: usize,
+    // bytes already dropped from the front of the buffer
+    offset: usize,
+    finished: bool,
+    signatures: Vec<Signature>,
+    longest: usize,
+}
+
+impl<R: Read> StreamLexer<R> {
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        Self::with_signatures(reader, chunk_size, &[MUL, DO, DONT])
+    }
+
+    pub fn with_signatures(reader: R, chunk_size: usize, signatures: &[Signature]) -> Self {
+        StreamLexer {
+            reader,
+            chunk_size: chunk_size.max(1),
+            buffer: Vec::new(),
+            position: 0,
+            offset: 0,
+            finished: false,
+            signatures: signatures.to_vec(),
+            longest: longest_call(signatures),
+        }
+    }
+
+    // Drops what's been scanned and reads the next chunk onto the end of what's left
+    fn refill(&mut self) -> Result<(), AocError> {
+        self.buffer.drain(..self.position);
+        self.offset += self.position;
+        self.position = 0;
+        let filled = self.buffer.len();
+        self.buffer.resize(filled + self.chunk_size, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        let read = read.inspect_err(|_| self.buffer.truncate(filled))?;
+        self.buffer.truncate(filled + read);
+        self.finished = read == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamLexer<R> {
+    type Item = Result<Token, AocError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.position < self.buffer.len()
+                && (self.finished || self.position + self.longest <= self.buffer.len())
+            {
+                let start = self.position;
+                if let Some((instruction, end)) = token_at(&self.buffer, start, &self.signatures) {
+                    self.position = end;
+                    return Some(Ok(Token {
+                        instruction,
+                        span: self.offset + start..self.offset + end,
+                    }));
                 }
+                self.position += 1;
+            }
+            if self.finished {
+                return None;
             }
-            Some(toggle_match) => {
-                toggle = Toggle::from(toggle_match.as_str());
+            if let Err(error) = self.refill() {
+                self.finished = true;
+                return Some(Err(error));
             }
         }
-    });
-    total
+    }
+}
+
+// What the program has done so far
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Machine {
+    pub enabled: bool,
+    pub total: usize,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Machine {
+            enabled: true,
+            total: 0,
+        }
+    }
+}
+
+pub type Handler = Box<dyn Fn(&mut Machine, &Instruction) -> Result<(), AocError>>;
+
+// Runs the instructions it has handlers for and ignores the rest. The lexer only looks for
+// operations with a registered handler.
+pub struct Interpreter {
+    handlers: HashMap<Signature, Handler>,
+}
+
+impl Interpreter {
+    // mul, always enabled
+    pub fn new() -> Self {
+        Interpreter {
+            handlers: HashMap::new(),
+        }
+        .register(MUL, |machine, instruction| {
+            if let (true, Instruction::Mul(a, b)) = (machine.enabled, instruction) {
+                machine.total = add(machine.total, mul(*a, *b)?)?;
+            }
+            Ok(())
+        })
+    }
+
+    // mul, which do() and don't() switch on and off
+    pub fn with_toggles() -> Self {
+        Self::new()
+            .register(DO, |machine, _| {
+                machine.enabled = true;
+                Ok(())
+            })
+            .register(DONT, |machine, _| {
+                machine.enabled = false;
+                Ok(())
+            })
+    }
+
+    // Replaces any handler already registered for the signature
+    pub fn register(
+        mut self,
+        signature: Signature,
+        handler: impl Fn(&mut Machine, &Instruction) -> Result<(), AocError> + 'static,
+    ) -> Self {
+        self.handlers.insert(signature, Box::new(handler));
+        self
+    }
+
+    pub fn run(&self, memory: &str) -> Result<Machine, AocError> {
+        let mut machine = Machine::default();
+        for token in Lexer::with_signatures(memory, &self.signatures()) {
+            budget::step()?;
+            self.execute(&mut machine, &token.instruction)?;
+        }
+        Ok(machine)
+    }
+
+    // Runs a program read `chunk_size` bytes at a time, see StreamLexer
+    pub fn run_reader(&self, reader: impl Read, chunk_size: usize) -> Result<Machine, AocError> {
+        let mut machine = Machine::default();
+        for token in StreamLexer::with_signatures(reader, chunk_size, &self.signatures()) {
+            budget::step()?;
+            self.execute(&mut machine, &token?.instruction)?;
+        }
+        Ok(machine)
+    }
+
+    // Runs the program and records every instruction it meets along the way, plus anything
+    // that looks like it was meant to be an instruction but isn't quite one
+    pub fn trace(&self, memory: &str) -> Result<Trace, AocError> {
+        let signatures = self.signatures();
+        let mut machine = Machine::default();
+        let mut steps = Vec::new();
+        for token in Lexer::with_signatures(memory, &signatures) {
+            budget::step()?;
+            let (enabled, before) = (machine.enabled, machine.total);
+            self.execute(&mut machine, &token.instruction)?;
+            let signature = token.instruction.signature();
+            steps.push(TraceStep {
+                offset: token.span.start,
+                instruction: signature.name,
+                operands: operands(&token.instruction),
+                enabled,
+                contribution: difference(machine.total, before)?,
+            });
+        }
+        Ok(Trace {
+            steps,
+            near_misses: find_near_misses(memory.as_bytes(), &signatures),
+            total: machine.total,
+        })
+    }
+
+    fn signatures(&self) -> Vec<Signature> {
+        self.handlers.keys().copied().collect()
+    }
+
+    fn execute(&self, machine: &mut Machine, instruction: &Instruction) -> Result<(), AocError> {
+        let handler = &self.handlers[&instruction.signature()];
+        handler(machine, instruction)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Everything the interpreter did with a program, for working out why a total isn't what it
+// should be. Serialises to JSON with to_json.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    pub near_misses: Vec<NearMiss>,
+    pub total: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    // byte offset of the instruction in memory
+    pub offset: usize,
+    pub instruction: &'static str,
+    pub operands: Vec<usize>,
+    // whether instructions were enabled when this one was reached
+    pub enabled: bool,
+    // how much this instruction changed the total by
+    pub contribution: isize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NearMiss {
+    pub offset: usize,
+    // the memory from the start of the name to where it stopped looking like an instruction
+    pub text: String,
+    pub kind: NearMissKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NearMissKind {
+    // well formed apart from whitespace, e.g. mul ( 2 , 4 )
+    Whitespace,
+    // an operand with more than three digits, e.g. mul(1234,5)
+    LongOperand,
+    // a name, bracket and some operand digits, then something else, e.g. mul(4*
+    BrokenOff,
+}
+
+impl Trace {
+    pub fn to_json(&self) -> Result<String, AocError> {
+        serde_json::to_string_pretty(self).map_err(|error| AocError::unreachable(error.to_string()))
+    }
+}
+
+fn operands(instruction: &Instruction) -> Vec<usize> {
+    match instruction {
+        Instruction::Mul(a, b) => vec![*a, *b],
+        Instruction::Do | Instruction::Dont => Vec::new(),
+        Instruction::Op(_, operands) => operands.clone(),
+    }
+}
+
+fn difference(after: usize, before: usize) -> Result<isize, AocError> {
+    let magnitude = isize::try_from(after.abs_diff(before)).map_err(|_| AocError::Overflow)?;
+    Ok(if after < before {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+// Every place a signature's name is followed by a bracket that the lexer passed over, where
+// the rest comes close enough to an instruction to be worth pointing out
+fn find_near_misses(memory: &[u8], signatures: &[Signature]) -> Vec<NearMiss> {
+    (0..memory.len())
+        .filter(|&start| token_at(memory, start, signatures).is_none())
+        .filter_map(|start| {
+            signatures.iter().find_map(|signature| {
+                let (kind, end) = near_miss_at(memory, start, *signature)?;
+                Some(NearMiss {
+                    offset: start,
+                    text: String::from_utf8_lossy(&memory[start..end]).into_owned(),
+                    kind,
+                })
+            })
+        })
+        .collect()
+}
+
+// Reads an instruction the way the lexer would, but lets whitespace and long operands through
+// and notes where it first went wrong
+fn near_miss_at(
+    memory: &[u8],
+    start: usize,
+    signature: Signature,
+) -> Option<(NearMissKind, usize)> {
+    if !memory[start..].starts_with(signature.name.as_bytes()) {
+        return None;
+    }
+    let mut position = start + signature.name.len();
+    let mut spaced = false;
+    let mut skip_whitespace = |position: &mut usize| {
+        while memory
+            .get(*position)
+            .is_some_and(|byte| byte.is_ascii_whitespace())
+        {
+            *position += 1;
+            spaced = true;
+        }
+    };
+    skip_whitespace(&mut position);
+    if memory.get(position) != Some(&b'(') {
+        return None;
+    }
+    position += 1;
+    let mut long = false;
+    let mut any_digits = false;
+    let mut broken = false;
+    for index in 0..signature.arity {
+        skip_whitespace(&mut position);
+        if index > 0 {
+            if memory.get(position) != Some(&b',') {
+                broken = true;
+                break;
+            }
+            position += 1;
+            skip_whitespace(&mut position);
+        }
+        let digits = memory[position..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            broken = true;
+            break;
+        }
+        any_digits = true;
+        long |= digits > 3;
+        position += digits;
+    }
+    if !broken {
+        skip_whitespace(&mut position);
+        broken = memory.get(position) != Some(&b')');
+    }
+    // include the byte it went wrong on, or the closing bracket
+    let end = (position + 1).min(memory.len());
+    match (long, broken, spaced) {
+        (true, _, _) => Some((NearMissKind::LongOperand, end)),
+        (false, false, true) => Some((NearMissKind::Whitespace, end)),
+        (false, true, _) if any_digits => Some((NearMissKind::BrokenOff, end)),
+        _ => None,
+    }
+}
+
+pub fn get_computer_instructions_sum(instructions: &str) -> Result<usize, AocError> {
+    Ok(Interpreter::new().run(instructions)?.total)
+}
+
+pub fn get_toggled_computer_instructions_sum(instructions: &str) -> Result<usize, AocError> {
+    Ok(Interpreter::with_toggles().run(instructions)?.total)
+}
+
+pub fn read_computer_instructions_sum(memory: impl Read) -> Result<usize, AocError> {
+    Ok(Interpreter::new().run_reader(memory, CHUNK)?.total)
+}
+
+pub fn read_toggled_computer_instructions_sum(memory: impl Read) -> Result<usize, AocError> {
+    Ok(Interpreter::with_toggles().run_reader(memory, CHUNK)?.total)
 }
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_03_EASY, DAY_03_HARD};
-    use crate::day_03::{get_computer_instructions_sum, get_toggled_computer_instructions_sum};
+    use crate::day_03::{
+        get_computer_instructions_sum, get_toggled_computer_instructions_sum,
+        read_computer_instructions_sum, read_toggled_computer_instructions_sum, Instruction,
+        Interpreter, Lexer, NearMiss, NearMissKind, Signature, StreamLexer, Token, Trace,
+        TraceStep,
+    };
+    use crate::error::AocError;
+    use crate::generators;
+    use regex::Regex;
+    use std::io::{self, Read};
 
     const INSTRUCTIONS: &str = include_str!("../resources/day_03/easy.txt");
+    const EXAMPLE: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
     #[test]
     fn easy() {
-        let left = get_computer_instructions_sum(INSTRUCTIONS);
+        let left = get_computer_instructions_sum(INSTRUCTIONS).unwrap();
         let right = DAY_03_EASY;
         assert_eq!(left, right)
     }
 
     #[test]
     fn hard() {
-        let left = get_toggled_computer_instructions_sum(INSTRUCTIONS);
+        let left = get_toggled_computer_instructions_sum(INSTRUCTIONS).unwrap();
         let right = DAY_03_HARD;
         assert_eq!(left, right)
     }
+
+    #[test]
+    fn lexes_example() {
+        let left = Lexer::new(EXAMPLE).collect::<Vec<_>>();
+        let token = |instruction, start, end| Token {
+            instruction,
+            span: start..end,
+        };
+        let right = [
+            token(Instruction::Mul(2, 4), 1, 9),
+            token(Instruction::Dont, 20, 27),
+            token(Instruction::Mul(5, 5), 28, 36),
+            token(Instruction::Mul(11, 8), 48, 57),
+            token(Instruction::Do, 59, 63),
+            token(Instruction::Mul(8, 5), 64, 72),
+        ];
+        assert_eq!(left, right);
+        assert_eq!(get_computer_instructions_sum(EXAMPLE).unwrap(), 161);
+        assert_eq!(get_toggled_computer_instructions_sum(EXAMPLE).unwrap(), 48);
+    }
+
+    #[test]
+    fn skips_broken_instructions() {
+        for memory in [
+            "mul(1234,5)",
+            "mul(1,2",
+            "mul(,2)",
+            "mul (1,2)",
+            "mul(1,2,3)",
+            "do(1)",
+        ] {
+            assert_eq!(Lexer::new(memory).count(), 0, "{}", memory);
+        }
+        let left = Lexer::new("mul(mul(2,3)").collect::<Vec<_>>();
+        let right = Token {
+            instruction: Instruction::Mul(2, 3),
+            span: 4..12,
+        };
+        assert_eq!(left, [right]);
+    }
+
+    #[test]
+    fn registered_operations() {
+        const ADD: Signature = Signature {
+            name: "add",
+            arity: 2,
+        };
+        // switches off when its operand is odd
+        const DONT_IF_ODD: Signature = Signature {
+            name: "dont_if_odd",
+            arity: 1,
+        };
+        let interpreter = Interpreter::with_toggles()
+            .register(ADD, |machine, instruction| {
+                if let (true, Instruction::Op(_, operands)) = (machine.enabled, instruction) {
+                    machine.total += operands.iter().sum::<usize>();
+                }
+                Ok(())
+            })
+            .register(DONT_IF_ODD, |machine, instruction| {
+                if let Instruction::Op(_, operands) = instruction {
+                    machine.enabled &= operands[0] % 2 == 0;
+                }
+                Ok(())
+            });
+        let memory = "add(1,2)mul(3,4)dont_if_odd(2)add(5)add(10,20)dont_if_odd(7)mul(9,9)do()";
+        let machine = interpreter.run(memory).unwrap();
+        assert_eq!(machine.total, 3 + 12 + 30);
+        assert!(machine.enabled);
+    }
+
+    // The puzzle's rules as regexes
+    fn reference_sums(memory: &str) -> (usize, usize) {
+        let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)|do\(\)|don't\(\)").unwrap();
+        let (mut all, mut enabled, mut on) = (0, 0, true);
+        for cap in re.captures_iter(memory) {
+            match &cap[0] {
+                "do()" => on = true,
+                "don't()" => on = false,
+                _ => {
+                    let (a, b) = (cap[1].parse::<usize>(), cap[2].parse::<usize>());
+                    let product = a.unwrap() * b.unwrap();
+                    all += product;
+                    enabled += if on { product } else { 0 };
+                }
+            }
+        }
+        (all, enabled)
+    }
+
+    #[test]
+    fn matches_reference() {
+        for seed in 0..500 {
+            let memory = generators::corrupted_memory(seed, 40);
+            let left = (
+                get_computer_instructions_sum(&memory).unwrap(),
+                get_toggled_computer_instructions_sum(&memory).unwrap(),
+            );
+            assert_eq!(left, reference_sums(&memory), "{}", memory);
+        }
+    }
+
+    #[test]
+    fn streamed() {
+        let left = read_computer_instructions_sum(INSTRUCTIONS.as_bytes()).unwrap();
+        assert_eq!(left, DAY_03_EASY);
+        let left = read_toggled_computer_instructions_sum(INSTRUCTIONS.as_bytes()).unwrap();
+        assert_eq!(left, DAY_03_HARD);
+    }
+
+    #[test]
+    fn split_across_chunks() {
+        let memory = "mul(12,3)don't()mul(4,5)do()mul(1,1)";
+        for chunk_size in 1..=memory.len() + 1 {
+            let left = StreamLexer::new(memory.as_bytes(), chunk_size)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(
+                left,
+                Lexer::new(memory).collect::<Vec<_>>(),
+                "{}",
+                chunk_size
+            );
+            let left = Interpreter::with_toggles().run_reader(memory.as_bytes(), chunk_size);
+            assert_eq!(left.unwrap().total, 36 + 1);
+        }
+    }
+
+    #[test]
+    fn streamed_matches_in_memory() {
+        let interpreter = Interpreter::with_toggles();
+        for seed in 0..100 {
+            let memory = generators::corrupted_memory(seed, 40);
+            let right = Lexer::new(&memory).collect::<Vec<_>>();
+            let total = interpreter.run(&memory).unwrap();
+            for chunk_size in [1, 2, 3, 5, 8, 13, 64] {
+                let left = StreamLexer::new(memory.as_bytes(), chunk_size)
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                assert_eq!(left, right, "{} in chunks of {}", memory, chunk_size);
+                let left = interpreter
+                    .run_reader(memory.as_bytes(), chunk_size)
+                    .unwrap();
+                assert_eq!(left, total);
+            }
+        }
+    }
+
+    // Hands out the memory a byte at a time, then fails
+    struct Failing<'a>(&'a [u8]);
+
+    impl Read for Failing<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Err(io::Error::other("disk on fire"));
+            };
+            buffer[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn read_errors() {
+        // padded so the lexer can see a whole mul from the start before the reader fails
+        let mut lexer = StreamLexer::new(Failing(b"mul(2,3)...."), 4);
+        assert_eq!(
+            lexer.next().unwrap().unwrap().instruction,
+            Instruction::Mul(2, 3)
+        );
+        assert_eq!(
+            lexer.next(),
+            Some(Err(AocError::Io("disk on fire".to_string())))
+        );
+        assert_eq!(lexer.next(), None);
+        let left = read_toggled_computer_instructions_sum(Failing(b"mul(2,3)"));
+        assert_eq!(left, Err(AocError::Io("disk on fire".to_string())));
+    }
+
+    #[test]
+    fn traces_example() {
+        let memory = "xmul(2,4)&don't()_mul(5,5)+mul(4*do()mul ( 2 , 4 )mul(1234,5)mul(8,5)";
+        let left = Interpreter::with_toggles().trace(memory).unwrap();
+        let step = |offset, instruction, operands: &[usize], enabled, contribution| TraceStep {
+            offset,
+            instruction,
+            operands: operands.to_vec(),
+            enabled,
+            contribution,
+        };
+        let near_miss = |offset, text: &str, kind| NearMiss {
+            offset,
+            text: text.to_string(),
+            kind,
+        };
+        let right = Trace {
+            steps: vec![
+                step(1, "mul", &[2, 4], true, 8),
+                step(10, "don't", &[], true, 0),
+                step(18, "mul", &[5, 5], false, 0),
+                step(33, "do", &[], false, 0),
+                step(61, "mul", &[8, 5], true, 40),
+            ],
+            near_misses: vec![
+                near_miss(27, "mul(4*", NearMissKind::BrokenOff),
+                near_miss(37, "mul ( 2 , 4 )", NearMissKind::Whitespace),
+                near_miss(50, "mul(1234,5)", NearMissKind::LongOperand),
+            ],
+            total: 48,
+        };
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn trace_to_json() {
+        let trace = Interpreter::with_toggles()
+            .trace("don't()mul(2,3)mul(2 ,3)")
+            .unwrap();
+        let left = serde_json::from_str::<serde_json::Value>(&trace.to_json().unwrap()).unwrap();
+        let right = serde_json::json!({
+            "steps": [
+                {"offset": 0, "instruction": "don't", "operands": [], "enabled": true, "contribution": 0},
+                {"offset": 7, "instruction": "mul", "operands": [2, 3], "enabled": false, "contribution": 0},
+            ],
+            "near_misses": [{"offset": 15, "text": "mul(2 ,3)", "kind": "whitespace"}],
+            "total": 0,
+        });
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn trace_matches_total() {
+        let interpreter = Interpreter::with_toggles();
+        let trace = interpreter.trace(INSTRUCTIONS).unwrap();
+        assert_eq!(trace.total, DAY_03_HARD);
+        let contributions = trace
+            .steps
+            .iter()
+            .map(|step| step.contribution)
+            .sum::<isize>();
+        assert_eq!(contributions as usize, DAY_03_HARD);
+        for seed in 0..50 {
+            let memory = generators::corrupted_memory(seed, 40);
+            let trace = interpreter.trace(&memory).unwrap();
+            assert_eq!(trace.total, interpreter.run(&memory).unwrap().total);
+            assert_eq!(trace.steps.len(), Lexer::new(&memory).count());
+        }
+    }
 }
@@ -1,12 +1,11 @@
+use crate::error::AocError;
+use crate::tile::rectangular;
+use std::collections::{HashMap, VecDeque};
+
 type WordSearch = Vec<Vec<char>>;
 
-macro_rules! unwrap_or_return_false {
-    ($op:expr) => {
-        match $op {
-            Some(value) => value,
-            None => return false,
-        }
-    };
+pub fn parse_input(input: &str) -> Result<WordSearch, AocError> {
+    rectangular(input.lines().map(|line| line.chars().collect()).collect())
 }
 
 trait Matrix<T> {
@@ -26,52 +25,14 @@ struct MatrixPointsIterator {
     column_length: usize,
 }
 
-#[derive(Clone, Debug, PartialEq)]
-struct Point {
-    x: usize,
-    y: usize,
-}
-
-impl Point {
-    fn shift(&self, direction: &Direction) -> Option<Point> {
-        match direction {
-            Direction::North => Some(Point {
-                x: self.x,
-                y: self.y.checked_sub(1)?,
-            }),
-            Direction::East => Some(Point {
-                x: self.x.checked_add(1)?,
-                y: self.y,
-            }),
-            Direction::South => Some(Point {
-                x: self.x,
-                y: self.y.checked_add(1)?,
-            }),
-            Direction::West => Some(Point {
-                x: self.x.checked_sub(1)?,
-                y: self.y,
-            }),
-            Direction::NorthEast => Some(Point {
-                x: self.x.checked_add(1)?,
-                y: self.y.checked_sub(1)?,
-            }),
-            Direction::NorthWest => Some(Point {
-                x: self.x.checked_sub(1)?,
-                y: self.y.checked_sub(1)?,
-            }),
-            Direction::SouthEast => Some(Point {
-                x: self.x.checked_add(1)?,
-                y: self.y.checked_add(1)?,
-            }),
-            Direction::SouthWest => Some(Point {
-                x: self.x.checked_sub(1)?,
-                y: self.y.checked_add(1)?,
-            }),
-        }
-    }
-}
-
-enum Direction {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
     North,
     South,
     East,
@@ -95,119 +56,819 @@ fn iter_directions() -> [Direction; 8] {
     ]
 }
 
-pub fn get_word_count(word: &str, word_search: &WordSearch) -> usize {
-    let word_chars = word.chars().collect::<Vec<_>>();
-    let mut count = 0;
+pub fn get_word_count(word: &str, word_search: &WordSearch) -> Result<usize, AocError> {
+    Ok(WordFinder::new(&[word])
+        .find(word_search, Overlap::Allowed)
+        .len())
+}
 
-    for y in 0..word_search.len() {
-        for x in 0..word_search[0].len() {
-            let start = Point { x, y };
-            for directions in iter_directions() {
-                if word_exists_in_direction(&start, &directions, &word_chars, word_search) {
-                    count += 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overlap {
+    // every match, however many letters it shares with others
+    Allowed,
+    // no letter is used twice by matches running along the same line in the same direction.
+    // Going along the line, a match is kept if it starts after the last kept one ends, the
+    // longest word winning when several end on the same letter.
+    Disallowed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WordMatch<'a> {
+    pub word: &'a str,
+    pub start: Point,
+    pub heading: Heading,
+}
+
+// One move from a letter to the next
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub dx: isize,
+    pub dy: isize,
+}
+
+impl From<Direction> for Step {
+    fn from(direction: Direction) -> Self {
+        let (dx, dy) = match direction {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+            Direction::NorthEast => (1, -1),
+            Direction::NorthWest => (-1, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::SouthWest => (-1, 1),
+        };
+        Step { dx, dy }
+    }
+}
+
+pub const KNIGHT: [Step; 8] = [
+    Step { dx: 1, dy: -2 },
+    Step { dx: 2, dy: -1 },
+    Step { dx: 2, dy: 1 },
+    Step { dx: 1, dy: 2 },
+    Step { dx: -1, dy: 2 },
+    Step { dx: -2, dy: 1 },
+    Step { dx: -2, dy: -1 },
+    Step { dx: -1, dy: -2 },
+];
+
+// Which way a match reads
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Heading {
+    Step(Step),
+    // along a snake, see Topology::SnakeRows
+    Snake { reversed: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Topology {
+    // words stop at the edges
+    #[default]
+    Bounded,
+    // stepping off one edge comes back on at the opposite one, so every line is a loop. A
+    // word can run round a loop at most once, it never uses the same letter twice.
+    Toroidal,
+    // the rows read one after another as a single line, alternately left to right and right
+    // to left, boustrophedon style, and words are read along it both ways. Steps don't apply.
+    SnakeRows,
+    // as SnakeRows, with the columns read top to bottom then bottom to top
+    SnakeColumns,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchRules {
+    pub topology: Topology,
+    // steps that don't move anywhere are left out
+    pub steps: Vec<Step>,
+}
+
+impl Default for SearchRules {
+    // the puzzle's rules, straight lines in the eight compass directions
+    fn default() -> Self {
+        SearchRules {
+            topology: Topology::Bounded,
+            steps: iter_directions().into_iter().map(Step::from).collect(),
+        }
+    }
+}
+
+// A path through the grid to read words along, with whether it loops back to its start
+struct Line {
+    heading: Heading,
+    points: Vec<Point>,
+    looped: bool,
+}
+
+// An Aho-Corasick automaton over a dictionary. Each state is a prefix of some word, with a
+// fallback to the longest proper suffix of it that's also a prefix, so every word ending at a
+// letter is found in a single pass along a line.
+pub struct WordFinder<'a> {
+    words: Vec<&'a str>,
+    states: Vec<State>,
+    // letters in the longest word
+    longest: usize,
+}
+
+#[derive(Default)]
+struct State {
+    next: HashMap<char, usize>,
+    fallback: usize,
+    // the words, by index, that end in this state, including through its fallbacks
+    words: Vec<usize>,
+}
+
+impl<'a> WordFinder<'a> {
+    // Empty words are never found
+    pub fn new(words: &[&'a str]) -> Self {
+        let mut states = vec![State::default()];
+        for (index, word) in words.iter().enumerate() {
+            if word.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for letter in word.chars() {
+                state = match states[state].next.get(&letter) {
+                    Some(next) => *next,
+                    None => {
+                        states.push(State::default());
+                        let next = states.len() - 1;
+                        states[state].next.insert(letter, next);
+                        next
+                    }
+                };
+            }
+            states[state].words.push(index);
+        }
+
+        // breadth first, so a state's fallback is always finished before the state itself
+        let mut queue = states[0].next.values().copied().collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            let next = states[state]
+                .next
+                .iter()
+                .map(|(l, n)| (*l, *n))
+                .collect::<Vec<_>>();
+            for (letter, next) in next {
+                let mut fallback = states[state].fallback;
+                let fallback = loop {
+                    if let Some(&target) = states[fallback].next.get(&letter) {
+                        break target;
+                    }
+                    if fallback == 0 {
+                        break 0;
+                    }
+                    fallback = states[fallback].fallback;
+                };
+                states[next].fallback = fallback;
+                let inherited = states[fallback].words.clone();
+                states[next].words.extend(inherited);
+                queue.push_back(next);
+            }
+        }
+        WordFinder {
+            words: words.to_vec(),
+            states,
+            longest: words
+                .iter()
+                .map(|word| word.chars().count())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    fn advance(&self, mut state: usize, letter: char) -> usize {
+        loop {
+            if let Some(&next) = self.states[state].next.get(&letter) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.states[state].fallback;
+        }
+    }
+
+    // Every match in every direction, under the puzzle's rules
+    pub fn find(&self, word_search: &WordSearch, overlap: Overlap) -> Vec<WordMatch<'a>> {
+        self.find_with(word_search, &SearchRules::default(), overlap)
+    }
+
+    // Every match along every line the rules make. Matches come line by line, one step after
+    // another, and in the order they end along each line.
+    pub fn find_with(
+        &self,
+        word_search: &WordSearch,
+        rules: &SearchRules,
+        overlap: Overlap,
+    ) -> Vec<WordMatch<'a>> {
+        let mut matches = Vec::new();
+        for line in lines(word_search, rules) {
+            let length = line.points.len();
+            // a loop is read round once, and on far enough for a word starting at its end
+            let wrap = if line.looped {
+                self.longest.min(length).saturating_sub(1)
+            } else {
+                0
+            };
+            let points = line.points.iter().chain(&line.points[..wrap]);
+            let mut state = 0;
+            // the first position along the line a kept match may start at
+            let mut free_from = 0;
+            // and the first one it may not reach, once a kept match wraps round to it
+            let mut first_kept = None;
+            for (end, point) in points.enumerate() {
+                state = self.advance(state, word_search[point.y][point.x]);
+                let ending = self.states[state]
+                    .words
+                    .iter()
+                    .map(|&index| self.words[index]);
+                let ending = ending
+                    .map(|word| (word, word.chars().count()))
+                    // round a loop shorter than the word it would reuse a cell
+                    .filter(|(_, letters)| *letters <= length)
+                    .filter_map(|(word, letters)| Some((word, (end + 1).checked_sub(letters)?)))
+                    .filter(|(_, start)| *start < length);
+                let found = |(word, start): (&'a str, usize)| WordMatch {
+                    word,
+                    start: line.points[start],
+                    heading: line.heading,
+                };
+                match overlap {
+                    Overlap::Allowed => matches.extend(ending.map(found)),
+                    Overlap::Disallowed => {
+                        let longest = ending
+                            .filter(|(_, start)| *start >= free_from)
+                            .filter(|_| first_kept.is_none_or(|first| end < length + first))
+                            .min_by_key(|(_, start)| *start);
+                        if let Some((word, start)) = longest {
+                            first_kept.get_or_insert(start);
+                            matches.push(found((word, start)));
+                            free_from = end + 1;
+                        }
+                    }
                 }
             }
         }
+        matches
     }
-    count
 }
 
-fn word_exists_in_direction(
-    start: &Point,
-    direction: &Direction,
-    word_chars: &[char],
-    word_search: &WordSearch,
-) -> bool {
-    let mut next_index = Some(start.clone());
+fn lines(word_search: &WordSearch, rules: &SearchRules) -> Vec<Line> {
+    let height = word_search.len();
+    let width = word_search.first().map_or(0, |row| row.len());
+    let steps = rules
+        .steps
+        .iter()
+        .filter(|step| (step.dx, step.dy) != (0, 0));
+    match rules.topology {
+        Topology::Bounded => steps
+            .flat_map(|step| bounded_lines(word_search, *step))
+            .collect(),
+        Topology::Toroidal => steps.flat_map(|step| loops(width, height, *step)).collect(),
+        Topology::SnakeRows => {
+            snake_lines(height, width, |line, along| Point { x: along, y: line })
+        }
+        Topology::SnakeColumns => {
+            snake_lines(width, height, |line, along| Point { x: line, y: along })
+        }
+    }
+}
 
-    for char in word_chars {
-        let current = unwrap_or_return_false!(next_index.take());
-        let ch = unwrap_or_return_false!(word_search.get_scalar(&current));
+fn offset(point: &Point, step: Step) -> Option<Point> {
+    Some(Point {
+        x: point.x.checked_add_signed(step.dx)?,
+        y: point.y.checked_add_signed(step.dy)?,
+    })
+}
 
-        if char != ch {
-            return false;
-        };
-        next_index = current.shift(direction);
+// Every line through the grid taking this step, each from the edge it starts at
+fn bounded_lines(word_search: &WordSearch, step: Step) -> Vec<Line> {
+    let inside = |point: &Point| word_search.get_scalar(point).is_some();
+    let backwards = Step {
+        dx: -step.dx,
+        dy: -step.dy,
+    };
+    let mut lines = Vec::new();
+    for (y, row) in word_search.iter().enumerate() {
+        for x in 0..row.len() {
+            let start = Point { x, y };
+            if offset(&start, backwards).is_some_and(|before| inside(&before)) {
+                continue;
+            }
+            let mut points = vec![start];
+            while let Some(next) = offset(points.last().unwrap(), step).filter(inside) {
+                points.push(next);
+            }
+            lines.push(Line {
+                heading: Heading::Step(step),
+                points,
+                looped: false,
+            });
+        }
     }
-    true
+    lines
 }
 
-pub fn get_cross_word_count(word_search: &WordSearch) -> usize {
-    let mut count = 0;
+// The loops a step makes on a grid with its edges joined up, every cell on exactly one
+fn loops(width: usize, height: usize, step: Step) -> Vec<Line> {
+    let wrap = |value: usize, by: isize, size: usize| {
+        (value as isize + by.rem_euclid(size as isize)) as usize % size
+    };
+    let mut seen = vec![vec![false; width]; height];
+    let mut lines = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let mut points = Vec::new();
+            let mut point = Point { x, y };
+            while !seen[point.y][point.x] {
+                seen[point.y][point.x] = true;
+                points.push(point);
+                point = Point {
+                    x: wrap(point.x, step.dx, width),
+                    y: wrap(point.y, step.dy, height),
+                };
+            }
+            if !points.is_empty() {
+                lines.push(Line {
+                    heading: Heading::Step(step),
+                    points,
+                    looped: true,
+                });
+            }
+        }
+    }
+    lines
+}
 
-    for y in 0..word_search.len() {
-        for x in 0..word_search[0].len() {
-            let start = Point { x, y };
-            count += xmas_found(&start, word_search) as usize;
+// The snake through `lines` lines of `length` cells, and the same snake backwards
+fn snake_lines(lines: usize, length: usize, point: impl Fn(usize, usize) -> Point) -> Vec<Line> {
+    let mut points = Vec::new();
+    for line in 0..lines {
+        if line % 2 == 0 {
+            points.extend((0..length).map(|along| point(line, along)));
+        } else {
+            points.extend((0..length).rev().map(|along| point(line, along)));
         }
     }
-    count
+    let backwards = points.iter().rev().copied().collect();
+    vec![
+        Line {
+            heading: Heading::Snake { reversed: false },
+            points,
+            looped: false,
+        },
+        Line {
+            heading: Heading::Snake { reversed: true },
+            points: backwards,
+            looped: false,
+        },
+    ]
 }
 
-fn xmas_found(start: &Point, word_search: &WordSearch) -> bool {
-    let origin = unwrap_or_return_false!(word_search.get_scalar(start));
+// The X-MAS puzzle shape, two MAS crossing on their A, in one of its four rotations
+const X_MAS: &str = "M.S\n.A.\nM.S";
 
-    if origin != &'A' {
-        return false;
-    };
+pub fn get_cross_word_count(word_search: &WordSearch) -> Result<usize, AocError> {
+    let x_mas = Stencil::parse(X_MAS)?;
+    Ok(count_placements(&x_mas, Symmetry::Rotations, word_search))
+}
+
+// A small grid of letters to look for in a word search, with `.` matching any letter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stencil {
+    cells: Vec<Vec<Option<char>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    // only the stencil as written
+    Fixed,
+    // the stencil turned through each quarter turn
+    Rotations,
+    // the rotations and their mirror images, all eight
+    RotationsAndReflections,
+}
+
+impl Stencil {
+    pub fn parse(rows: &str) -> Result<Self, AocError> {
+        let cells = rows
+            .lines()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| (cell != '.').then_some(cell))
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        if cells.first().is_none_or(|row| row.is_empty()) {
+            return Err(AocError::parse("empty stencil"));
+        }
+        Ok(Stencil {
+            cells: rectangular(cells)?,
+        })
+    }
 
-    // Check left Diagonal
-    let upper_left_index = unwrap_or_return_false!(start.shift(&Direction::NorthWest));
-    let upper_left = unwrap_or_return_false!(word_search.get_scalar(&upper_left_index));
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn width(&self) -> usize {
+        self.cells[0].len()
+    }
 
-    let lower_right_index = unwrap_or_return_false!(start.shift(&Direction::SouthEast));
-    let lower_right = unwrap_or_return_false!(word_search.get_scalar(&lower_right_index));
+    // A quarter turn clockwise
+    pub fn rotate(&self) -> Stencil {
+        let cells = (0..self.width())
+            .map(|x| (0..self.height()).rev().map(|y| self.cells[y][x]).collect())
+            .collect();
+        Stencil { cells }
+    }
 
-    if !((upper_left == &'M' && lower_right == &'S') || (upper_left == &'S' && lower_right == &'M'))
-    {
-        return false;
+    // Mirrored left to right
+    pub fn reflect(&self) -> Stencil {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        Stencil { cells }
     }
 
-    let upper_right_index = unwrap_or_return_false!(start.shift(&Direction::NorthEast));
-    let upper_right = unwrap_or_return_false!(word_search.get_scalar(&upper_right_index));
-    let lower_left_index = unwrap_or_return_false!(start.shift(&Direction::SouthWest));
-    let lower_left = unwrap_or_return_false!(word_search.get_scalar(&lower_left_index));
+    // The distinct stencils the symmetry allows, this one first. A symmetric stencil has fewer
+    // than the full set, so a placement is never counted twice for looking the same turned
+    // round.
+    pub fn variants(&self, symmetry: Symmetry) -> Vec<Stencil> {
+        let mut variants = vec![self.clone()];
+        if symmetry != Symmetry::Fixed {
+            for _ in 0..3 {
+                let turned = variants.last().unwrap().rotate();
+                variants.push(turned);
+            }
+        }
+        if symmetry == Symmetry::RotationsAndReflections {
+            let reflected = variants.iter().map(Stencil::reflect).collect::<Vec<_>>();
+            variants.extend(reflected);
+        }
+        let mut distinct = Vec::new();
+        for variant in variants {
+            if !distinct.contains(&variant) {
+                distinct.push(variant);
+            }
+        }
+        distinct
+    }
 
-    if !((upper_right == &'M' && lower_left == &'S') || (upper_right == &'S' && lower_left == &'M'))
-    {
-        return false;
+    fn fits_at(&self, top_left: &Point, word_search: &WordSearch) -> bool {
+        self.cells.iter().enumerate().all(|(dy, row)| {
+            row.iter().enumerate().all(|(dx, cell)| {
+                let point = Point {
+                    x: top_left.x + dx,
+                    y: top_left.y + dy,
+                };
+                match (cell, word_search.get_scalar(&point)) {
+                    (_, None) => false,
+                    (None, Some(_)) => true,
+                    (Some(letter), Some(found)) => letter == found,
+                }
+            })
+        })
     }
+}
 
-    true
+// Where a stencil was found: the cell under its top left corner, and which of
+// stencil.variants(symmetry) it was
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub top_left: Point,
+    pub variant: usize,
+}
+
+pub fn find_placements(
+    stencil: &Stencil,
+    symmetry: Symmetry,
+    word_search: &WordSearch,
+) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    for (variant, stencil) in stencil.variants(symmetry).iter().enumerate() {
+        for (y, row) in word_search.iter().enumerate() {
+            for x in 0..row.len() {
+                let top_left = Point { x, y };
+                if stencil.fits_at(&top_left, word_search) {
+                    placements.push(Placement { top_left, variant });
+                }
+            }
+        }
+    }
+    placements
+}
+
+pub fn count_placements(stencil: &Stencil, symmetry: Symmetry, word_search: &WordSearch) -> usize {
+    find_placements(stencil, symmetry, word_search).len()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_04_EASY, DAY_04_HARD};
-    use crate::day_04::{get_cross_word_count, get_word_count, WordSearch};
+    use crate::day_04::{
+        count_placements, find_placements, get_cross_word_count, get_word_count, parse_input,
+        Direction, Heading, Overlap, Placement, Point, SearchRules, Stencil, Step, Symmetry,
+        Topology, WordFinder, WordMatch, WordSearch, KNIGHT,
+    };
+    use crate::error::AocError;
+    use crate::generators::{self, Rng};
+    use std::collections::HashSet;
 
     const INPUT: &str = include_str!("../resources/day_04/easy.txt");
     const PREAMBLE: &str = include_str!("../resources/day_04/preamble.txt");
 
-    fn parse_input(input: &str) -> WordSearch {
-        input.split('\n').map(|l| l.chars().collect()).collect()
-    }
-
     #[test]
     fn preamble() {
-        let word_search = parse_input(PREAMBLE);
-        let left = get_word_count("XMAS", &word_search);
+        let word_search = parse_input(PREAMBLE).unwrap();
+        let left = get_word_count("XMAS", &word_search).unwrap();
         let right = 18;
         assert_eq!(left, right);
     }
     #[test]
     fn easy() {
-        let word_search = parse_input(INPUT);
-        let left = get_word_count("XMAS", &word_search);
+        let word_search = parse_input(INPUT).unwrap();
+        let left = get_word_count("XMAS", &word_search).unwrap();
         let right = DAY_04_EASY;
         assert_eq!(left, right);
     }
 
     #[test]
     fn hard() {
-        let word_search = parse_input(INPUT);
-        let left = get_cross_word_count(&word_search);
+        let word_search = parse_input(INPUT).unwrap();
+        let left = get_cross_word_count(&word_search).unwrap();
         let right = DAY_04_HARD;
         assert_eq!(left, right);
     }
+
+    // Looks for each word starting from every cell with every step, wrapping round the edges
+    // on a torus as long as no letter is used twice
+    fn reference_matches<'a>(
+        words: &[&'a str],
+        word_search: &WordSearch,
+        rules: &SearchRules,
+    ) -> HashSet<WordMatch<'a>> {
+        let toroidal = rules.topology == Topology::Toroidal;
+        let (height, width) = (word_search.len() as isize, word_search[0].len() as isize);
+        let mut matches = HashSet::new();
+        for (y, row) in word_search.iter().enumerate() {
+            for x in 0..row.len() {
+                for step in &rules.steps {
+                    for word in words.iter().filter(|word| !word.is_empty()) {
+                        let cells = (0..word.chars().count() as isize)
+                            .map(|i| (x as isize + i * step.dx, y as isize + i * step.dy))
+                            .map(|(x, y)| match toroidal {
+                                true => (x.rem_euclid(width), y.rem_euclid(height)),
+                                false => (x, y),
+                            })
+                            .collect::<Vec<_>>();
+                        let inside = cells
+                            .iter()
+                            .all(|(x, y)| (0..width).contains(x) && (0..height).contains(y));
+                        if !inside || cells.iter().collect::<HashSet<_>>().len() < cells.len() {
+                            continue;
+                        }
+                        let read = cells
+                            .iter()
+                            .map(|(x, y)| word_search[*y as usize][*x as usize]);
+                        if read.eq(word.chars()) {
+                            matches.insert(WordMatch {
+                                word,
+                                start: Point { x, y },
+                                heading: Heading::Step(*step),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    #[test]
+    fn finds_every_word() {
+        let words = ["XMAS", "MAS", "SAM", "AS", "X", "MASXMAS", "SS", ""];
+        for seed in 0..200 {
+            let mut rng = Rng::new(seed);
+            let word_search =
+                parse_input(&generators::word_search(seed, 1 + rng.below(8))).unwrap();
+            let dictionary = &words[rng.below(words.len())..];
+            let finder = WordFinder::new(dictionary);
+            let knight = KNIGHT.to_vec();
+            let rule_sets = [
+                SearchRules::default(),
+                SearchRules {
+                    topology: Topology::Toroidal,
+                    ..SearchRules::default()
+                },
+                SearchRules {
+                    topology: Topology::Bounded,
+                    steps: knight.clone(),
+                },
+                SearchRules {
+                    topology: Topology::Toroidal,
+                    steps: knight,
+                },
+            ];
+            for rules in rule_sets {
+                let left = finder.find_with(&word_search, &rules, Overlap::Allowed);
+                let right = reference_matches(dictionary, &word_search, &rules);
+                assert_eq!(left.len(), right.len(), "{:?}", rules);
+                assert_eq!(left.into_iter().collect::<HashSet<_>>(), right);
+            }
+        }
+    }
+
+    #[test]
+    fn wraps_round() {
+        let word_search = parse_input("MASX\nSAMX").unwrap();
+        let rules = SearchRules {
+            topology: Topology::Toroidal,
+            steps: vec![Direction::East.into()],
+        };
+        let finder = WordFinder::new(&["XMAS", "XSAM", "MASXM"]);
+        let found = |overlap| {
+            finder
+                .find_with(&word_search, &rules, overlap)
+                .into_iter()
+                .map(|found| (found.word, found.start.x, found.start.y))
+                .collect::<Vec<_>>()
+        };
+        // MASXM would need the M twice
+        assert_eq!(found(Overlap::Allowed), [("XMAS", 3, 0), ("XSAM", 3, 1)]);
+
+        // round a loop of three there's room for one AA without sharing a letter
+        let word_search = parse_input("AAA").unwrap();
+        let finder = WordFinder::new(&["AA"]);
+        assert_eq!(
+            finder
+                .find_with(&word_search, &rules, Overlap::Allowed)
+                .len(),
+            3
+        );
+        assert_eq!(
+            finder
+                .find_with(&word_search, &rules, Overlap::Disallowed)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn knight_moves() {
+        let word_search = parse_input("X....\n..M..\n....A").unwrap();
+        let rules = SearchRules {
+            topology: Topology::Bounded,
+            steps: KNIGHT.to_vec(),
+        };
+        let left = WordFinder::new(&["XMA"]).find_with(&word_search, &rules, Overlap::Allowed);
+        let right = WordMatch {
+            word: "XMA",
+            start: Point { x: 0, y: 0 },
+            heading: Heading::Step(Step { dx: 2, dy: 1 }),
+        };
+        assert_eq!(left, [right]);
+        // a step that goes nowhere is ignored rather than read forever
+        let rules = SearchRules {
+            topology: Topology::Toroidal,
+            steps: vec![Step { dx: 0, dy: 0 }],
+        };
+        assert!(WordFinder::new(&["X"])
+            .find_with(&word_search, &rules, Overlap::Allowed)
+            .is_empty());
+    }
+
+    #[test]
+    fn snakes() {
+        // the rows snake XMA SA. ..., the columns X.. .AM AS.
+        let word_search = parse_input("XMA\n.AS\n...").unwrap();
+        let finder = WordFinder::new(&["XMAS", "SAMX", "SAM"]);
+        let found = |topology| {
+            let rules = SearchRules {
+                topology,
+                ..SearchRules::default()
+            };
+            finder
+                .find_with(&word_search, &rules, Overlap::Allowed)
+                .into_iter()
+                .map(|found| (found.word, found.start.x, found.start.y, found.heading))
+                .collect::<Vec<_>>()
+        };
+        let forward = Heading::Snake { reversed: false };
+        let backward = Heading::Snake { reversed: true };
+        assert_eq!(
+            found(Topology::SnakeRows),
+            [
+                ("XMAS", 0, 0, forward),
+                ("SAM", 2, 1, backward),
+                ("SAMX", 2, 1, backward)
+            ]
+        );
+        assert_eq!(found(Topology::SnakeColumns), [("SAM", 2, 1, backward)]);
+    }
+
+    #[test]
+    fn non_overlapping() {
+        let word_search = parse_input("XMASAMXMAS\nAAAAAAAAAA").unwrap();
+        let finder = WordFinder::new(&["MAS", "XMAS", "SAMX", "AA", "AAA"]);
+        let east = |overlap| {
+            finder
+                .find(&word_search, overlap)
+                .into_iter()
+                .filter(|found| found.heading == Heading::Step(Direction::East.into()))
+                .map(|found| (found.word, found.start.x, found.start.y))
+                .collect::<Vec<_>>()
+        };
+        let left = east(Overlap::Disallowed);
+        // MAS ends on the same S as each XMAS, the longer word wins
+        let right = [
+            ("XMAS", 0, 0),
+            ("XMAS", 6, 0),
+            ("AA", 0, 1),
+            ("AA", 2, 1),
+            ("AA", 4, 1),
+            ("AA", 6, 1),
+            ("AA", 8, 1),
+        ];
+        assert_eq!(left, right);
+        // SAMX shares its S with the first XMAS and its X with the second
+        assert!(east(Overlap::Allowed).contains(&("SAMX", 3, 0)));
+        assert_eq!(east(Overlap::Allowed).len(), 2 + 2 + 1 + 9 + 8);
+    }
+
+    #[test]
+    fn preamble_crosses() {
+        let word_search = parse_input(PREAMBLE).unwrap();
+        assert_eq!(get_cross_word_count(&word_search).unwrap(), 9);
+    }
+
+    #[test]
+    fn stencil_variants() {
+        let stencil = Stencil::parse("XM\n.A").unwrap();
+        assert_eq!(stencil.rotate(), Stencil::parse(".X\nAM").unwrap());
+        assert_eq!(stencil.reflect(), Stencil::parse("MX\nA.").unwrap());
+        assert_eq!(stencil.rotate().rotate().rotate().rotate(), stencil);
+        assert_eq!(stencil.variants(Symmetry::Fixed).len(), 1);
+        assert_eq!(stencil.variants(Symmetry::Rotations).len(), 4);
+        assert_eq!(stencil.variants(Symmetry::RotationsAndReflections).len(), 8);
+        // a plus sign looks the same however it's turned
+        let plus = Stencil::parse(".M.\nMAM\n.M.").unwrap();
+        assert_eq!(plus.variants(Symmetry::RotationsAndReflections), [plus]);
+
+        assert_eq!(Stencil::parse(""), Err(AocError::parse("empty stencil")));
+        assert!(Stencil::parse("XM\nA").is_err());
+    }
+
+    #[test]
+    fn lists_placements() {
+        let word_search = parse_input("XMAS\nMASX\nXMAS").unwrap();
+        let column = Stencil::parse("X\nM\nX").unwrap();
+        let placement = |x, y, variant| Placement {
+            top_left: Point { x, y },
+            variant,
+        };
+        let left = find_placements(&column, Symmetry::Fixed, &word_search);
+        assert_eq!(left, [placement(0, 0, 0)]);
+        // turned right round it's the same column again, which isn't counted twice, and no row
+        // reads XMX
+        assert_eq!(column.variants(Symmetry::Rotations).len(), 2);
+        let left = find_placements(&column, Symmetry::Rotations, &word_search);
+        assert_eq!(left, [placement(0, 0, 0)]);
+        // an A with an X diagonally next to it, in any of four corners
+        let corner = Stencil::parse("A.\n.X").unwrap();
+        let left = count_placements(&corner, Symmetry::RotationsAndReflections, &word_search);
+        assert_eq!(left, 4);
+    }
+
+    // The X-MAS check as first written, looking for M and S on both diagonals around each A
+    fn reference_cross_count(word_search: &WordSearch) -> usize {
+        let at = |x: usize, y: usize| word_search.get(y).and_then(|row| row.get(x)).copied();
+        let mut count = 0;
+        for (y, row) in word_search.iter().enumerate().skip(1) {
+            for x in 1..row.len().saturating_sub(1) {
+                let diagonal =
+                    |a, b| matches!((a, b), (Some('M'), Some('S')) | (Some('S'), Some('M')));
+                if at(x, y) == Some('A')
+                    && diagonal(at(x - 1, y - 1), at(x + 1, y + 1))
+                    && diagonal(at(x + 1, y - 1), at(x - 1, y + 1))
+                {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn crosses_match_reference() {
+        for seed in 0..200 {
+            let word_search = parse_input(&generators::word_search(seed, 1 + seed as usize % 12));
+            let word_search = word_search.unwrap();
+            let left = get_cross_word_count(&word_search).unwrap();
+            assert_eq!(left, reference_cross_count(&word_search));
+        }
+    }
 }
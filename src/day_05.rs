@@ -1,125 +1,116 @@
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use crate::arith::add;
+use crate::budget;
+use crate::digraph::DiGraph;
+use crate::error::AocError;
 
-fn day_05(ordering_rules: &[(usize, usize)], page_orders: &[Vec<usize>]) -> usize {
-    let lookup = graph_from_ordering_rules(ordering_rules);
+type Rule = (usize, usize);
+type Update = Vec<usize>;
+
+// `before|after` ordering rules, a blank line, then one comma separated update per line
+pub fn parse_input(input: &str) -> Result<(Vec<Rule>, Vec<Update>), AocError> {
+    let (ordering_rules, page_orders) = input
+        .split_once("\n\n")
+        .ok_or_else(|| AocError::parse("expected a blank line between the rules and the updates"))?;
+
+    let ordering_rules = ordering_rules
+        .lines()
+        .map(|line| {
+            let (before, after) = line
+                .split_once('|')
+                .ok_or_else(|| AocError::parse(format!("invalid ordering rule {:?}", line)))?;
+            Ok((before.parse()?, after.parse()?))
+        })
+        .collect::<Result<_, AocError>>()?;
+
+    let page_orders = page_orders
+        .lines()
+        .map(|line| line.split(',').map(|page| Ok(page.parse()?)).collect())
+        .collect::<Result<_, AocError>>()?;
+
+    Ok((ordering_rules, page_orders))
+}
+
+pub fn day_05(ordering_rules: &[(usize, usize)], page_orders: &[Vec<usize>]) -> Result<usize, AocError> {
+    let rules = DiGraph::from_edges(ordering_rules);
     page_orders
         .iter()
-        .map(|page_order| {
-            let is_valid = page_order.windows(2).all(|w| {
-                let set = lookup.get(&w[0]);
-                match set {
-                    None => false,
-                    Some(set) => set.contains(&w[1]),
-                }
-            });
-
-            if is_valid {
-                page_order[page_order.len() / 2]
+        .try_fold(0, |total, page_order| {
+            budget::step()?;
+            if is_valid_page_order(page_order, &rules) {
+                add(total, get_middle_page(page_order)?)
             } else {
-                0
+                Ok(total)
             }
         })
-        .sum()
 }
 
-fn day_05_hard(ordering_rules: &[(usize, usize)], page_orders: &[Vec<usize>]) -> usize {
-    let lookup = graph_from_ordering_rules(ordering_rules);
-    page_orders.iter().map(|page_order| {
-        let is_valid = page_order.windows(2).all(|w| {
-            let set = lookup.get(&w[0]);
-            match set {
-                None => false,
-                Some(set) => set.contains(&w[1]),
+pub fn day_05_hard(
+    ordering_rules: &[(usize, usize)],
+    page_orders: &[Vec<usize>],
+) -> Result<usize, AocError> {
+    let rules = DiGraph::from_edges(ordering_rules);
+    page_orders
+        .iter()
+        .try_fold(0, |total, page_order| {
+            budget::step()?;
+            if is_valid_page_order(page_order, &rules) {
+                Ok(total)
+            } else {
+                add(total, get_middle_page(&sort_page_order(page_order, &rules)?)?)
             }
-        });
-
-        if is_valid {
-            0
-        } else {
-            let mut page_order = page_order.clone();
-            sort_page_order(&mut page_order, &lookup);
-            page_order[page_order.len() / 2]
-        }
-    }).sum()
+        })
 }
 
+fn get_middle_page(page_order: &[usize]) -> Result<usize, AocError> {
+    page_order
+        .get(page_order.len() / 2)
+        .copied()
+        .ok_or_else(|| AocError::domain("update without any pages"))
+}
 
-fn graph_from_ordering_rules(ordering_rules: &[(usize, usize)]) -> HashMap<usize, HashSet<usize>> {
-    let mut graph = HashMap::new();
-    ordering_rules.iter().for_each(|(key, value)| {
-        let mut set = match graph.get_mut(key) {
-            Some(set) => set,
-            None => {
-                let set = HashSet::new();
-                graph.insert(*key, set);
-                graph.get_mut(key).unwrap()
-            }
-        };
-        set.insert(*value);
-    });
-    graph
+fn is_valid_page_order(page_order: &[usize], rules: &DiGraph<usize>) -> bool {
+    page_order
+        .windows(2)
+        .all(|w| rules.contains_edge(&w[0], &w[1]))
 }
-fn sort_page_order(page_order: &mut [usize], lookup: &HashMap<usize, HashSet<usize>>){
-    page_order.sort_by(|a, b| {
-        if let Some(set) = lookup.get(a)  {
-            if set.contains(b) {
-                return Ordering::Less
-            }
-        };
 
-        if let Some(set) = lookup.get(b)  {
-            if set.contains(a) {
-                return Ordering::Greater
-            }
-        };
-        Ordering::Equal
-    })
+// The full rule set is usually cyclic, only the rules between the pages of one update
+// have to agree with each other.
+fn sort_page_order(page_order: &[usize], rules: &DiGraph<usize>) -> Result<Vec<usize>, AocError> {
+    Ok(rules.induced_subgraph(page_order).topological_sort()?)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_05_EASY, DAY_05_HARD};
-    use crate::day_05::{day_05, day_05_hard};
+    use crate::day_05::{day_05, day_05_hard, parse_input};
+    use crate::digraph::CycleError;
+    use crate::error::AocError;
 
     const INPUT: &str = include_str!("../resources/day_05/easy.txt");
 
-    fn parse_input(input: &str) -> (Vec<(usize, usize)>, Vec<Vec<usize>>) {
-        let mut split = input.split("\n\n");
-        let ordering_rules = split.next().unwrap();
-        let page_orders = split.next().unwrap();
-
-        let ordering_rules = ordering_rules
-            .split("\n")
-            .map(|line| {
-                let mut split_line = line.split("|");
-                let a = split_line.next().unwrap().parse().unwrap();
-                let b = split_line.next().unwrap().parse().unwrap();
-                (a, b)
-            })
-            .collect();
-
-        let page_orders = page_orders
-            .split("\n")
-            .map(|line| line.split(",").map(|i| i.parse().unwrap()).collect())
-            .collect();
-
-        (ordering_rules, page_orders)
-    }
-
     #[test]
     fn easy() {
-        let (ordering_rules, page_orders) = parse_input(INPUT);
-        let left = day_05(&ordering_rules, &page_orders);
+        let (ordering_rules, page_orders) = parse_input(INPUT).unwrap();
+        let left = day_05(&ordering_rules, &page_orders).unwrap();
         let right = DAY_05_EASY;
         assert_eq!(left, right);
     }
 
     #[test]
     fn hard() {
-        let (ordering_rules, page_orders) = parse_input(INPUT);
-        let left = day_05_hard(&ordering_rules, &page_orders);
+        let (ordering_rules, page_orders) = parse_input(INPUT).unwrap();
+        let left = day_05_hard(&ordering_rules, &page_orders).unwrap();
         let right = DAY_05_HARD;
         assert_eq!(left, right)
     }
+
+    #[test]
+    fn contradictory_rules() {
+        let left = day_05_hard(&[(1, 2), (2, 3), (3, 1)], &[vec![3, 2, 1]]);
+        let right = Err(AocError::from(CycleError {
+            cycle: vec![2, 3, 1],
+        }));
+        assert_eq!(left, right)
+    }
 }
@@ -1,10 +1,23 @@
+use crate::bitset::{DirectionalGridSet, GridSet};
+use crate::budget;
 use crate::common::{Direction, Matrix, Point};
+use crate::error::AocError;
+use crate::parallel::Parallelism;
+use crate::tile::{parse_grid, rectangular, tile};
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::iter::Extend;
 use std::mem::needs_drop;
 
-type Lab = Vec<Vec<char>>;
+type Lab = Vec<Vec<LabTile>>;
+
+tile! {
+    pub(crate) enum LabTile {
+        Floor => '.',
+        Obstruction => '#',
+        Guard => '^',
+    }
+}
 
 macro_rules! unwrap_or_break {
     ($op:expr) => {
@@ -15,96 +28,217 @@ macro_rules! unwrap_or_break {
     };
 }
 
-pub fn find_patrol_path(lab: &Lab) -> Option<Vec<Point>> {
+pub fn parse_input(input: &str) -> Result<Lab, AocError> {
+    rectangular(parse_grid(input)?)
+}
+
+pub fn find_patrol_path(lab: &Lab) -> Result<Vec<Point>, AocError> {
     let mut patrol_path = vec![find_guard_location(lab)?];
     let mut guard_direction = Direction::North;
+    let mut visited = DirectionalGridSet::for_grid(lab);
+    let mut turns = 0;
 
     loop {
+        budget::step()?;
         let current = patrol_path.last().unwrap();
         let next = unwrap_or_break!(current.shift(&guard_direction));
         let ch = unwrap_or_break!(lab.get_scalar(&next));
-        if ch == &'.' || ch == &'^' {
+        if ch == &LabTile::Floor || ch == &LabTile::Guard {
+            if !visited.insert(&guard_direction, current).ok_or_else(off_the_grid)? {
+                return Err(never_leaves());
+            }
+            turns = 0;
             patrol_path.push(next);
         } else {
+            turns += 1;
+            if turns == 4 {
+                return Err(never_leaves());
+            }
             guard_direction = guard_direction.rotate_90_degrees_clockwise();
         }
     }
-    Some(patrol_path)
+    Ok(patrol_path)
+}
+
+fn never_leaves() -> AocError {
+    AocError::domain("the guard never leaves the lab")
+}
+
+fn off_the_grid() -> AocError {
+    AocError::unreachable("the guard stood off the lab or faced a diagonal")
 }
 
-pub fn find_obstructions(lab: &Lab) -> Option<Vec<Point>> {
+pub fn find_obstructions(lab: &Lab) -> Result<Vec<Point>, AocError> {
     let mut obstructions = Vec::new();
-    let mut visited = Vec::new();
+    let mut visited = DirectionalGridSet::for_grid(lab);
+    let mut inner_visited = DirectionalGridSet::for_grid(lab);
     let mut direction = Direction::North;
     let mut pos = find_guard_location(lab)?;
-    let mut cannot_obstruct = HashSet::new();
+    let mut cannot_obstruct = GridSet::for_grid(lab);
+    let mut turns = 0;
 
     loop {
+        budget::step()?;
         let Some(next_pos) = pos.shift(&direction) else {
-            return Some(obstructions);
+            return Ok(obstructions);
         };
 
         let Some(next_tile) = lab.get_scalar(&next_pos) else {
-            return Some(obstructions);
+            return Ok(obstructions);
         };
 
-        if next_tile == &'#' {
+        if next_tile == &LabTile::Obstruction {
+            turns += 1;
+            if turns == 4 {
+                return Err(never_leaves());
+            }
             direction = direction.rotate_90_degrees_clockwise();
             continue;
         }
+        turns = 0;
 
-        visited.push((direction.clone(), pos));
-        cannot_obstruct.insert(pos);
+        if !visited.insert(&direction, &pos).ok_or_else(off_the_grid)? {
+            return Err(never_leaves());
+        }
+        cannot_obstruct.insert(&pos).ok_or_else(off_the_grid)?;
 
 
         let obstruction = next_pos;
 
         if !cannot_obstruct.contains(&obstruction) {
-            let mut inner_visited = Vec::new();
-            let mut direction = direction.rotate_90_degrees_clockwise();
-            let mut pos = pos.clone();
-            loop {
-                let Some(next_pos) = pos.shift(&direction) else {
-                    break;
-                };
-                let Some(next_tile) = lab.get_scalar(&next_pos) else {
-                    break;
-                };
-
-                if next_tile == &'#' || next_pos == obstruction {
-                    direction = direction.rotate_90_degrees_clockwise();
-                    continue;
-                } else if visited.contains(&(direction.clone(), pos))
-                    || inner_visited.contains(&(direction.clone(), pos))
-                {
-                    obstructions.push(obstruction);
+            inner_visited.clear();
+            let walked_before = |direction: &Direction, pos: &Point| visited.contains(direction, pos);
+            if loops_back(lab, obstruction, pos, direction, walked_before, &mut inner_visited)? {
+                obstructions.push(obstruction);
+            }
+        }
+        pos = next_pos;
+    }
+}
 
-                    break;
-                } else {
-                    inner_visited.push((direction.clone(), pos));
-                    pos = next_pos;
-                }
+// Same answer as find_obstructions, in the same order. The main path is walked first to find
+// where each obstruction would be met, then every candidate is checked on its own, without the
+// shortcut of stopping as soon as she rejoins the path walked so far.
+pub fn find_obstructions_with(lab: &Lab, parallelism: Parallelism) -> Result<Vec<Point>, AocError> {
+    if parallelism.is_sequential() {
+        return find_obstructions(lab);
+    }
+    let candidates = find_candidate_obstructions(lab)?;
+    let loops = parallelism.map(&candidates, |&(obstruction, pos, direction)| {
+        let mut inner_visited = DirectionalGridSet::for_grid(lab);
+        loops_back(lab, obstruction, pos, direction, |_, _| false, &mut inner_visited)
+    });
+    candidates
+        .into_iter()
+        .zip(loops)
+        .filter_map(|((obstruction, _, _), loops)| match loops {
+            Ok(loops) => loops.then_some(Ok(obstruction)),
+            Err(error) => Some(Err(error)),
+        })
+        .collect()
+}
+
+// Every tile on the patrol path, the first time she is about to walk onto it, along with where
+// she is and which way she's facing at that moment
+fn find_candidate_obstructions(lab: &Lab) -> Result<Vec<(Point, Point, Direction)>, AocError> {
+    let mut candidates = Vec::new();
+    let mut visited = DirectionalGridSet::for_grid(lab);
+    let mut direction = Direction::North;
+    let mut pos = find_guard_location(lab)?;
+    let mut cannot_obstruct = GridSet::for_grid(lab);
+    let mut turns = 0;
+
+    loop {
+        budget::step()?;
+        let Some(next_pos) = pos.shift(&direction) else {
+            return Ok(candidates);
+        };
+        let Some(next_tile) = lab.get_scalar(&next_pos) else {
+            return Ok(candidates);
+        };
+
+        if next_tile == &LabTile::Obstruction {
+            turns += 1;
+            if turns == 4 {
+                return Err(never_leaves());
             }
+            direction = direction.rotate_90_degrees_clockwise();
+            continue;
+        }
+        turns = 0;
+
+        if !visited.insert(&direction, &pos).ok_or_else(off_the_grid)? {
+            return Err(never_leaves());
+        }
+        cannot_obstruct.insert(&pos).ok_or_else(off_the_grid)?;
+        if !cannot_obstruct.contains(&next_pos) {
+            candidates.push((next_pos, pos, direction));
         }
         pos = next_pos;
     }
 }
 
-fn find_guard_location(lab: &Lab) -> Option<Point> {
-    for y in 0..lab.len() {
-        for x in 0..lab[0].len() {
-            let ch = &lab[y][x];
-            if ch == &'^' {
-                return Some(Point { x, y });
+// Turns her at an obstruction placed in front of her and walks on until she either leaves or
+// is stuck in a loop. She's looping once she repeats herself or lands on a state for which
+// walked_before holds.
+fn loops_back(
+    lab: &Lab,
+    obstruction: Point,
+    mut pos: Point,
+    direction: Direction,
+    walked_before: impl Fn(&Direction, &Point) -> bool,
+    inner_visited: &mut DirectionalGridSet<Direction>,
+) -> Result<bool, AocError> {
+    let mut direction = direction.rotate_90_degrees_clockwise();
+    let mut turns = 0;
+    loop {
+        budget::step()?;
+        let Some(next_pos) = pos.shift(&direction) else {
+            return Ok(false);
+        };
+        let Some(next_tile) = lab.get_scalar(&next_pos) else {
+            return Ok(false);
+        };
+
+        if next_tile == &LabTile::Obstruction || next_pos == obstruction {
+            // boxed in on all four sides, she spins on the spot forever
+            turns += 1;
+            if turns == 4 {
+                return Ok(true);
+            }
+            direction = direction.rotate_90_degrees_clockwise();
+            continue;
+        }
+        turns = 0;
+        if walked_before(&direction, &pos) || inner_visited.contains(&direction, &pos) {
+            return Ok(true);
+        }
+        inner_visited.insert(&direction, &pos).ok_or_else(off_the_grid)?;
+        pos = next_pos;
+    }
+}
+
+fn find_guard_location(lab: &Lab) -> Result<Point, AocError> {
+    for (y, row) in lab.iter().enumerate() {
+        for (x, ch) in row.iter().enumerate() {
+            if ch == &LabTile::Guard {
+                return Ok(Point { x, y });
             }
         }
     }
-    None
+    Err(AocError::domain("no guard found in the lab"))
 }
 #[cfg(test)]
 mod tests {
-    use crate::common::{Matrix, Point};
-    use crate::day_06::{find_obstructions, find_patrol_path, Lab};
+    use crate::common::{Direction, Matrix, Point};
+    use crate::day_06::{
+        find_guard_location, find_obstructions, find_obstructions_with, find_patrol_path,
+        parse_input, Lab, LabTile,
+    };
+    use crate::differential::{assert_agree, small_size};
+    use crate::generators;
+    use crate::error::AocError;
+    use crate::parallel::Parallelism;
     use std::collections::HashSet;
     use std::hash::Hash;
     use crate::answers::{DAY_06_EASY, DAY_06_HARD};
@@ -113,16 +247,9 @@ mod tests {
     const PREAMBLE: &str = include_str!("../resources/day_06/preamble.txt");
     const DEBUG: &str = include_str!("../resources/day_06/debug.txt");
 
-    fn parse_input(input: &str) -> Lab {
-        input
-            .split("\n")
-            .map(|line| line.chars().collect())
-            .collect()
-    }
-
     #[test]
     fn easy() {
-        let lab = parse_input(INPUT);
+        let lab = parse_input(INPUT).unwrap();
         let mut left = HashSet::new();
         left.extend(find_patrol_path(&lab).unwrap());
         let left = left.len();
@@ -131,7 +258,7 @@ mod tests {
     }
     #[test]
     fn hard() {
-        let lab = parse_input(INPUT);
+        let lab = parse_input(INPUT).unwrap();
         let mut left = HashSet::new();
         left.extend(find_obstructions(&lab).unwrap());
 
@@ -148,4 +275,84 @@ mod tests {
         }
         assert_eq!(left.len(), DAY_06_HARD);
     }
+
+    #[test]
+    fn missing_guard() {
+        let lab = parse_input("..#\n...").unwrap();
+        let right = Err(AocError::domain("no guard found in the lab"));
+        assert_eq!(find_patrol_path(&lab), right);
+        assert_eq!(find_obstructions(&lab), right);
+    }
+
+    #[test]
+    fn guard_never_leaves() {
+        let right = Err(AocError::domain("the guard never leaves the lab"));
+        for lab in [".#..\n...#\n#^..\n..#.", ".#.\n#^#\n.#."] {
+            let lab = parse_input(lab).unwrap();
+            assert_eq!(find_patrol_path(&lab), right);
+            assert_eq!(find_obstructions(&lab), right);
+        }
+    }
+
+    // Tries an obstruction on every floor tile and walks the guard until she leaves or
+    // repeats herself
+    fn reference_obstructions(lab: &Lab) -> HashSet<Point> {
+        let start = find_guard_location(lab).unwrap();
+        let mut obstructions = HashSet::new();
+        for y in 0..lab.len() {
+            for x in 0..lab[y].len() {
+                if lab[y][x] != LabTile::Floor {
+                    continue;
+                }
+                let mut blocked = lab.clone();
+                blocked[y][x] = LabTile::Obstruction;
+                if guard_loops(&blocked, start) {
+                    obstructions.insert(Point { x, y });
+                }
+            }
+        }
+        obstructions
+    }
+
+    fn guard_loops(lab: &Lab, start: Point) -> bool {
+        let mut seen = HashSet::new();
+        let (mut position, mut direction) = (start, Direction::North);
+        while seen.insert((position, direction)) {
+            let Some(next) = position.shift(&direction) else {
+                return false;
+            };
+            match lab.get_scalar(&next) {
+                None => return false,
+                Some(LabTile::Obstruction) => direction = direction.rotate_90_degrees_clockwise(),
+                Some(_) => position = next,
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn matches_reference() {
+        assert_agree(
+            2000,
+            |seed| generators::lab(seed, small_size(seed, 10)),
+            |input| {
+                let lab = parse_input(input).unwrap();
+                find_obstructions(&lab).unwrap().into_iter().collect::<HashSet<_>>()
+            },
+            |input| reference_obstructions(&parse_input(input).unwrap()),
+        );
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut inputs = vec![INPUT.to_string(), PREAMBLE.to_string()];
+        inputs.extend((0..200).map(|seed| generators::lab(seed, small_size(seed, 12))));
+        for input in inputs {
+            let lab = parse_input(&input).unwrap();
+            let right = find_obstructions(&lab);
+            for workers in [2, 3, 8] {
+                assert_eq!(find_obstructions_with(&lab, Parallelism::with_workers(workers)), right);
+            }
+        }
+    }
 }
@@ -1,34 +1,59 @@
+use crate::arith::{add, mul, Wide};
+use crate::budget;
+use crate::error::AocError;
+use crate::parallel::Parallelism;
 use std::collections::VecDeque;
-use std::num::ParseIntError;
 
 struct Equation{
     result: isize,
     operators: VecDeque<isize>,
 }
 
-fn solve(mut operators: &VecDeque<isize>, expected: isize) -> bool {
+// `test value: numbers` on every line
+pub fn parse_input(input: &str) -> Result<Vec<(VecDeque<isize>, isize)>, AocError> {
+    input
+        .lines()
+        .map(|line| {
+            let (result, operators) = line
+                .split_once(':')
+                .ok_or_else(|| AocError::parse(format!("missing ':' in {:?}", line)))?;
+            let operators = operators
+                .split_whitespace()
+                .map(|n| Ok(n.parse()?))
+                .collect::<Result<_, AocError>>()?;
+            Ok((operators, result.parse()?))
+        })
+        .collect()
+}
+
+fn solve(mut operators: &VecDeque<isize>, expected: isize) -> Result<bool, AocError> {
     // How solve?
 
 
 
     let mut operators = operators.clone();
     let mut results= VecDeque::new();
-    let first = operators.pop_front().unwrap();
+    let first = pop_first(&mut operators)? as Wide;
+    let expected = expected as Wide;
 
     results.push_front(first);
 
     loop {
         let Some(next_op) = operators.pop_front() else {
-            return results.into_iter().any(|r| r == expected)
+            return Ok(results.into_iter().any(|r| r == expected))
         };
+        let next_op = next_op as Wide;
 
         let n = results.len();
 
         for _ in 0..n {
-            let prev_op = results.pop_front().unwrap();
+            budget::step()?;
+            let prev_op = results
+                .pop_front()
+                .ok_or_else(|| AocError::unreachable("fewer results than were counted"))?;
 
-            let add_result = prev_op + next_op;
-            let mul_result = prev_op * next_op;
+            let add_result = add(prev_op, next_op)?;
+            let mul_result = mul(prev_op, next_op)?;
 
             if add_result <= expected {
                 results.push_back(add_result);
@@ -40,33 +65,39 @@ fn solve(mut operators: &VecDeque<isize>, expected: isize) -> bool {
     }
 }
 
-fn solve_hard(mut operators: &VecDeque<isize>, expected: isize) -> bool {
+fn solve_hard(mut operators: &VecDeque<isize>, expected: isize) -> Result<bool, AocError> {
     // How solve?
 
 
 
     let mut operators = operators.clone();
     let mut results= VecDeque::new();
-    let first = operators.pop_front().unwrap();
+    let first = pop_first(&mut operators)? as Wide;
+    let expected = expected as Wide;
 
     results.push_front(first);
 
     loop {
         let Some(next_op) = operators.pop_front() else {
-            return results.into_iter().any(|r| r == expected)
+            return Ok(results.into_iter().any(|r| r == expected))
         };
+        let next_op = next_op as Wide;
 
         let n = results.len();
 
         for _ in 0..n {
-            let prev_op = results.pop_front().unwrap();
-
-            let add_result = prev_op + next_op;
-            let mul_result = prev_op * next_op;
-            let concat_result = concat(prev_op, next_op).unwrap();
-
-            if concat_result <= expected {
-                results.push_back(concat_result)
+            budget::step()?;
+            let prev_op = results
+                .pop_front()
+                .ok_or_else(|| AocError::unreachable("fewer results than were counted"))?;
+
+            let add_result = add(prev_op, next_op)?;
+            let mul_result = mul(prev_op, next_op)?;
+            // a concatenation that doesn't fit is too big to be expected as well
+            if let Some(concat_result) = concat(prev_op, next_op) {
+                if concat_result <= expected {
+                    results.push_back(concat_result)
+                }
             }
 
             if add_result <= expected {
@@ -79,63 +110,118 @@ fn solve_hard(mut operators: &VecDeque<isize>, expected: isize) -> bool {
     }
 }
 
-fn concat(left: isize, right: isize) -> Result<isize, ParseIntError> {
-    (left.to_string() + &right.to_string()).parse()
+fn pop_first(operators: &mut VecDeque<isize>) -> Result<isize, AocError> {
+    operators
+        .pop_front()
+        .ok_or_else(|| AocError::domain("equation without any numbers"))
 }
-fn find_sum(input: &mut Vec<(VecDeque<isize>, isize)>) -> isize {
-    input
-        .iter_mut()
-        .filter_map(|(operators, expected)| {
-            if solve(operators, *expected) {
-                Some(*expected)
-            } else {
-                None
-            }
-        }).sum()
 
+fn concat(left: Wide, right: Wide) -> Option<Wide> {
+    let mut shift: Wide = 10;
+    while shift <= right {
+        shift = shift.checked_mul(10)?;
+    }
+    left.checked_mul(shift)?.checked_add(right)
+}
+pub fn find_sum(input: &mut Vec<(VecDeque<isize>, isize)>) -> Result<isize, AocError> {
+    find_sum_with(input, Parallelism::sequential())
+}
+
+pub fn find_sum_hard(input: &mut Vec<(VecDeque<isize>, isize)>) -> Result<isize, AocError> {
+    find_sum_hard_with(input, Parallelism::sequential())
+}
+
+pub fn find_sum_with(
+    input: &[(VecDeque<isize>, isize)],
+    parallelism: Parallelism,
+) -> Result<isize, AocError> {
+    let verdicts = parallelism.map(input, |(operators, expected)| solve(operators, *expected));
+    sum_solvable(input, verdicts)
+}
+
+pub fn find_sum_hard_with(
+    input: &[(VecDeque<isize>, isize)],
+    parallelism: Parallelism,
+) -> Result<isize, AocError> {
+    let verdicts = parallelism.map(input, |(operators, expected)| solve_hard(operators, *expected));
+    sum_solvable(input, verdicts)
 }
 
-fn find_sum_hard(input: &mut Vec<(VecDeque<isize>, isize)>) -> isize {
+fn sum_solvable(
+    input: &[(VecDeque<isize>, isize)],
+    verdicts: Vec<Result<bool, AocError>>,
+) -> Result<isize, AocError> {
     input
-        .iter_mut()
-        .filter_map(|(operators, expected)| {
-            if solve_hard(operators, *expected) {
-                Some(*expected)
+        .iter()
+        .zip(verdicts)
+        .try_fold(0, |total, ((_, expected), solvable)| {
+            if solvable? {
+                add(total, *expected)
             } else {
-                None
+                Ok(total)
             }
-        }).sum()
-
-
+        })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::VecDeque;
     use crate::answers::{DAY_07_EASY, DAY_07_HARD};
-    use crate::day_07::{find_sum, find_sum_hard, Equation};
+    use crate::day_07::{find_sum, find_sum_hard, find_sum_hard_with, find_sum_with, parse_input};
+    use crate::generators;
+    use crate::parallel::Parallelism;
 
     const INPUT: &str = include_str!("../resources/day_07/day_07.txt");
-    fn parse_input(input: &str) -> Vec<(VecDeque<isize>, isize)> {
-        input.split("\n").map(|line| {
-            let (result, operators) = line.split_once(":").unwrap();
-            let result = result.parse().unwrap() ;
-            let operators = operators.split_whitespace().map(|n| n.parse().unwrap()).collect();
-            (operators, result)
-        }).collect()
-    }
     #[test]
     fn easy() {
-        let mut lines = parse_input(INPUT);
-        let left = find_sum(&mut lines);
+        let mut lines = parse_input(INPUT).unwrap();
+        let left = find_sum(&mut lines).unwrap();
         assert_eq!(left, DAY_07_EASY)
 
     }
     #[test]
     fn hard() {
-        let mut lines = parse_input(INPUT);
-        let left = find_sum_hard(&mut lines);
+        let mut lines = parse_input(INPUT).unwrap();
+        let left = find_sum_hard(&mut lines).unwrap();
         assert_eq!(left, DAY_07_HARD)
 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut inputs = vec![INPUT.to_string()];
+        inputs.extend((0..20).map(|seed| generators::calibration_lines(seed, 50)));
+        for input in inputs {
+            let mut lines = parse_input(&input).unwrap();
+            let easy = find_sum(&mut lines);
+            let hard = find_sum_hard(&mut lines);
+            for workers in [2, 3, 8] {
+                let parallelism = Parallelism::with_workers(workers);
+                assert_eq!(find_sum_with(&lines, parallelism), easy);
+                assert_eq!(find_sum_hard_with(&lines, parallelism), hard);
+            }
+        }
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn widened_intermediates() {
+        use std::collections::VecDeque;
+
+        // every concatenation along the way goes past isize::MAX
+        let mut lines = vec![(VecDeque::from([isize::MAX / 2, 2, 1]), isize::MAX)];
+        let left = find_sum_hard(&mut lines);
+        assert_eq!(left, Ok(isize::MAX));
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn overflow() {
+        use crate::error::AocError;
+
+        // negative numbers keep the products below the test value while they grow past i128
+        let input = "0: -9000000000000000000 9000000000000000000 9000000000000000000";
+        let mut lines = parse_input(input).unwrap();
+        assert_eq!(find_sum(&mut lines), Err(AocError::Overflow));
+        assert_eq!(find_sum_hard(&mut lines), Err(AocError::Overflow));
+    }
+}
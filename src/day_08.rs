@@ -1,8 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::num::{TryFromIntError};
+use crate::budget;
+use crate::error::AocError;
+use crate::number_theory::gcd;
+use crate::tile::rectangular;
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash, Copy)]
-struct Point<T: Copy> {
+pub(crate) struct Point<T: Copy> {
     x: T,
     y: T,
 }
@@ -26,6 +30,10 @@ impl TryFrom<Point<isize>> for Index {
     }
 }
 
+pub fn parse_input(input: &str) -> Result<Vec<Vec<char>>, AocError> {
+    rectangular(input.lines().map(|line| line.chars().collect()).collect())
+}
+
 impl TryFrom<Index> for Point<isize> {
     type Error = TryFromIntError;
     fn try_from(value: Index) -> Result<Self, Self::Error> {
@@ -34,20 +42,23 @@ impl TryFrom<Index> for Point<isize> {
 }
 
 
-fn find_all_anti_nodes(matrix: &Vec<Vec<char>>) -> HashSet<Index> {
+pub fn find_all_anti_nodes(matrix: &Vec<Vec<char>>) -> Result<HashSet<Index>, AocError> {
     let mut anti_nodes = HashSet::new();
-    let antenna_positions = find_antenna_positions(matrix);
+    let antenna_positions = find_antenna_positions(matrix)?;
     for antennas in antenna_positions.values() {
-        anti_nodes.extend(get_anti_nodes(antennas, matrix));
+        anti_nodes.extend(get_anti_nodes(antennas, matrix)?);
     }
-    anti_nodes
+    Ok(anti_nodes)
 }
-fn get_anti_nodes<M: Matrix<char>>(antennas: &Vec<Point<isize>>, matrix: &M) -> Vec<Index> {
+fn get_anti_nodes<M: Matrix<char>>(
+    antennas: &Vec<Point<isize>>,
+    matrix: &M,
+) -> Result<Vec<Index>, AocError> {
     let mut anti_nodes = Vec::new();
     let mut i = 1;
     for first in &antennas[0..antennas.len() - 1] {
         for second in &antennas[i..] {
-
+            budget::step()?;
 
             let anti_node = get_anti_node(first, second);
             if let Some(anti_node) = anti_node {
@@ -70,45 +81,60 @@ fn get_anti_nodes<M: Matrix<char>>(antennas: &Vec<Point<isize>>, matrix: &M) ->
         }
         i += 1
     }
-    anti_nodes
+    Ok(anti_nodes)
 }
 
+// None when the anti node can't even be represented, which puts it well off the grid
 fn get_anti_node(first: &Point<isize>, second: &Point<isize>) -> Option<Point<isize>> {
-    let diff_x = first.x.checked_sub(second.x).unwrap();
-    let diff_y = first.y.checked_sub(second.y).unwrap();
+    let diff_x = first.x.checked_sub(second.x)?;
+    let diff_y = first.y.checked_sub(second.y)?;
 
-    let x = first.x.checked_add(diff_x).unwrap();
-    let y = first.y.checked_add(diff_y).unwrap();
+    let x = first.x.checked_add(diff_x)?;
+    let y = first.y.checked_add(diff_y)?;
     Some(Point { x, y })
 }
 
-fn find_antinodes_hard(matrix: &Vec<Vec<char>>) -> Vec<Point<isize>> {
+pub fn find_antinodes_hard(matrix: &Vec<Vec<char>>) -> Result<Vec<Point<isize>>, AocError> {
     let mut anti_nodes = Vec::new();
-    let antenna_positions = find_antenna_positions(matrix);
+    let antenna_positions = find_antenna_positions(matrix)?;
     for antennas in antenna_positions.values() {
         let mut i = 1;
         for first in &antennas[0..antennas.len() - 1] {
             for second in &antennas[i..] {
-                let nodes = get_all_anti_nodes(&[*first, *second], matrix);
+                budget::step()?;
+                let nodes = get_all_anti_nodes(&[*first, *second], matrix)?;
                 anti_nodes.extend(nodes);
-                let nodes = get_all_anti_nodes(&[*second, *first], matrix);
+                let nodes = get_all_anti_nodes(&[*second, *first], matrix)?;
                 anti_nodes.extend(nodes)
             }
             i += 1
         }
     }
 
-    anti_nodes
+    Ok(anti_nodes)
 }
-fn get_all_anti_nodes(segment: &[Point<isize>; 2], matrix: &Vec<Vec<char>> ) -> Vec<Point<isize>> {
+fn get_all_anti_nodes(
+    segment: &[Point<isize>; 2],
+    matrix: &Vec<Vec<char>>,
+) -> Result<Vec<Point<isize>>, AocError> {
     let (first, second) = (segment[0], segment[1]);
-    let diff_x = first.x.checked_sub(second.x).unwrap();
-    let diff_y = first.y.checked_sub(second.y).unwrap();
+    let diff_x = first.x.checked_sub(second.x).ok_or(AocError::Overflow)?;
+    let diff_y = first.y.checked_sub(second.y).ok_or(AocError::Overflow)?;
+
+    // step to the next grid point on the line, which can sit between the two antennas
+    let divisor = gcd(diff_x, diff_y).ok_or(AocError::Overflow)?;
+    if divisor == 0 {
+        return Err(AocError::unreachable("two antennas share a position"));
+    }
+    let diff_x = diff_x / divisor;
+    let diff_y = diff_y / divisor;
 
     let mut anti_nodes = Vec::new();
 
-    let mut anti_node = first.clone();
+    // walk from the second antenna so the points between the pair are covered as well
+    let mut anti_node = second.clone();
     loop {
+        budget::step()?;
         let Ok(index)= anti_node.try_into() else {
             break
         };
@@ -118,26 +144,28 @@ fn get_all_anti_nodes(segment: &[Point<isize>; 2], matrix: &Vec<Vec<char>> ) ->
         }
         anti_nodes.push(anti_node);
 
-        let x = anti_node.x.checked_add(diff_x).unwrap();
-        let y = anti_node.y.checked_add(diff_y).unwrap();
+        let (Some(x), Some(y)) = (anti_node.x.checked_add(diff_x), anti_node.y.checked_add(diff_y)) else {
+            break
+        };
         anti_node = Point {x, y}
     }
-    anti_nodes
+    Ok(anti_nodes)
 }
 
-fn find_antenna_positions(matrix: &Vec<Vec<char>>) -> HashMap<char, Vec<Point<isize>>> {
+fn find_antenna_positions(
+    matrix: &Vec<Vec<char>>,
+) -> Result<HashMap<char, Vec<Point<isize>>>, AocError> {
     let mut antennas = HashMap::new();
-    for row in 0..matrix.len() {
-        for column in 0..matrix[0].len() {
+    for (row, line) in matrix.iter().enumerate() {
+        for (column, ch) in line.iter().enumerate() {
             let index = Index { x: column, y: row };
-            let ch = matrix.get_scalar(&index).unwrap();
             if ch != &'.' {
                 let mut common_antennas = antennas.entry(*ch).or_insert_with(Vec::new);
-                common_antennas.push(index.try_into().unwrap());
+                common_antennas.push(index.try_into().map_err(|_| AocError::Overflow)?);
             }
         }
     }
-    antennas
+    Ok(antennas)
 }
 
 #[cfg(test)]
@@ -145,19 +173,15 @@ mod tests {
     use std::arch::aarch64::vreinterpret_f32_f64;
     use std::collections::{HashMap, HashSet};
     use crate::answers::{DAY_08_EASY, DAY_08_HARD};
-    use crate::day_08::{find_all_anti_nodes, find_antinodes_hard, Index, Matrix};
+    use crate::day_08::{find_all_anti_nodes, find_antinodes_hard, parse_input, Index, Matrix};
 
     const PREAMBLE: &str = include_str!("../resources/day_08/preamble.txt");
     const EASY: &str = include_str!("../resources/day_08/easy.txt");
 
-    fn parse_input(input: &str) -> Vec<Vec<char>> {
-        input.lines().map(|l| l.chars().collect()).collect()
-    }
-
     #[test]
     fn preamble() {
-        let input = parse_input(PREAMBLE);
-        let left = find_all_anti_nodes(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = find_all_anti_nodes(&input).unwrap();
         for row in 0..input.len() {
             for column in 0..input[row].len() {
                 let ind = Index { x: column, y: row };
@@ -175,14 +199,21 @@ mod tests {
     }
     #[test]
     fn easy() {
-        let left = find_all_anti_nodes(&parse_input(EASY)).len();
+        let left = find_all_anti_nodes(&parse_input(EASY).unwrap()).unwrap().len();
         let right = DAY_08_EASY;
         assert_eq!(left, right);
     }
     #[test]
+    fn hard_reduces_steps() {
+        let input = parse_input("..........\n...A......\n..........\n.......A..\n..........").unwrap();
+        let left = find_antinodes_hard(&input).unwrap().iter().collect::<HashSet<_>>().len();
+        let right = 5;
+        assert_eq!(left, right);
+    }
+    #[test]
     fn hard() {
 
-        let left = find_antinodes_hard(&parse_input(EASY)).iter().collect::<HashSet<_>>().len();
+        let left = find_antinodes_hard(&parse_input(EASY).unwrap()).unwrap().iter().collect::<HashSet<_>>().len();
         let right = DAY_08_HARD;
         assert_eq!(left, right);
     }
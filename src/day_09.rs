@@ -1,57 +1,70 @@
+use crate::arith::{add, mul, sum};
+use crate::budget;
 use crate::common::Point;
+use crate::error::AocError;
+use crate::interval_set::IntervalSet;
 use std::collections::VecDeque;
+use std::ops::Range;
 
 type Block = Option<usize>;
-fn parse_input(input: &str) -> Vec<Block> {
+// The disk map is a single line, a newline at the end of the file is fine
+pub fn parse_input(input: &str) -> Result<Vec<Block>, AocError> {
     let mut id = 0;
     let mut blocks = Vec::new();
 
     let mut is_block = true;
 
-    input.chars().for_each(|ch| {
+    for (index, ch) in input.trim_end().chars().enumerate() {
+        let n = parse_length(index, ch)?;
         if is_block {
-            let n = ch.to_digit(10).unwrap();
             for _ in 0..n {
                 blocks.push(Block::Some(id));
             }
             is_block = false;
             id += 1
         } else {
-            let n = ch.to_digit(10).unwrap();
             for _ in 0..n {
                 blocks.push(Block::None)
             }
             is_block = true;
         }
-    });
-    blocks
+    }
+    Ok(blocks)
+}
+
+fn parse_length(index: usize, ch: char) -> Result<usize, AocError> {
+    ch.to_digit(10)
+        .map(|n| n as usize)
+        .ok_or_else(|| AocError::parse(format!("expected a digit at {}, found {:?}", index, ch)))
 }
 
-trait Compress {
-    fn compress_blocks(&mut self);
-    fn compress_files(&mut self);
+pub(crate) trait Compress {
+    fn compress_blocks(&mut self) -> Result<(), AocError>;
+    fn compress_files(&mut self) -> Result<(), AocError>;
 }
 
 impl Compress for [Block] {
-    fn compress_blocks(&mut self) {
+    fn compress_blocks(&mut self) -> Result<(), AocError> {
         let mut i = 0;
-        let mut stop = self.len() - 1;
+        let Some(mut stop) = self.len().checked_sub(1) else {
+            return Ok(());
+        };
         while i < stop {
+            budget::step()?;
             match self[i] {
                 Some(_) => {
                     i += 1;
                     continue;
                 }
                 None => {
-                    // Skip all the dots
-                    while let None = self[stop] {
+                    // Skip all the dots, a disk with no files left to move has only dots
+                    while stop > i && self[stop].is_none() {
                         stop -= 1;
                     }
 
                     while stop > i {
-                        match self[stop] {
-                            Some(_) => {
-                                let last = self[stop].take().unwrap();
+                        match self[stop].take() {
+                            Some(last) => {
                                 self[i] = Some(last);
                                 stop -= 1;
                                 break;
@@ -64,20 +77,24 @@ impl Compress for [Block] {
                 }
             }
         }
+        Ok(())
     }
 
-    fn compress_files(&mut self) {
+    fn compress_files(&mut self) -> Result<(), AocError> {
 
-        let mut last_index = self.len() -1;
+        let Some(mut last_index) = self.len().checked_sub(1) else {
+            return Ok(());
+        };
         loop {
+            budget::step()?;
             let Some(file_stop) = find_previous_file_stop(self, last_index) else {
-                return;
+                return Ok(());
             };
-            let file_start = find_file_start(self, file_stop);
+            let file_start = find_file_start(self, file_stop)?;
 
-            try_move_file_stopping_at(self, file_start, file_stop);
+            try_move_file_stopping_at(self, file_start, file_stop)?;
             let Some(next_index) = file_start.checked_sub(1) else {
-                return
+                return Ok(())
             };
             last_index = next_index;
         }
@@ -88,11 +105,16 @@ impl Compress for [Block] {
 
 // failures
 // it either finds no file
-fn try_move_file_stopping_at(blocks: &mut [Block], file_start: usize, file_stop: usize) {
+fn try_move_file_stopping_at(
+    blocks: &mut [Block],
+    file_start: usize,
+    file_stop: usize,
+) -> Result<(), AocError> {
 
     let file_length = file_stop - file_start + 1; // add one because a file of length one's diff will be zero
     let mut index = 0;
     loop {
+        budget::step()?;
         let Some(free_space_start) = find_next_free_space_start(blocks, index) else {
             break
         };
@@ -103,7 +125,9 @@ fn try_move_file_stopping_at(blocks: &mut [Block], file_start: usize, file_stop:
         let free_space_stop = find_free_space_stop(blocks, free_space_start);
         let free_space_length = free_space_stop - free_space_start+ 1;
         if file_length <= free_space_length {
-            let Some(file_id) = blocks[file_start] else {panic!()};
+            let Some(file_id) = blocks[file_start] else {
+                return Err(AocError::unreachable(format!("no file starts at block {}", file_start)));
+            };
             // you should be using file_length
             for i in free_space_start..(free_space_start + file_length) {
                 blocks[i] = Some(file_id)
@@ -120,7 +144,7 @@ fn try_move_file_stopping_at(blocks: &mut [Block], file_start: usize, file_stop:
             }
         }
     }
-
+    Ok(())
 }
 
 fn find_previous_file_stop(blocks: &[Block], from: usize) -> Option<usize> {
@@ -134,7 +158,7 @@ fn find_previous_file_stop(blocks: &[Block], from: usize) -> Option<usize> {
 fn find_next_free_space_start(blocks: &[Block], from: usize) -> Option<usize> {
     let mut free_space_start = from;
     while let Some(_) = blocks[free_space_start] {
-        free_space_start = free_space_start.checked_add(1).unwrap();
+        free_space_start = free_space_start.checked_add(1)?;
         if free_space_start >= blocks.len() {
             return None
         }
@@ -143,8 +167,9 @@ fn find_next_free_space_start(blocks: &[Block], from: usize) -> Option<usize> {
 }
 
 
-fn find_file_start(blocks: &[Block], file_stop: usize) -> usize {
-    let file_id = &blocks[file_stop].unwrap();
+fn find_file_start(blocks: &[Block], file_stop: usize) -> Result<usize, AocError> {
+    let file_id = &blocks[file_stop]
+        .ok_or_else(|| AocError::unreachable(format!("no file ends at block {}", file_stop)))?;
     let mut file_start = file_stop;
 
     while let Some(previous_index) = file_start.checked_sub(1) {
@@ -156,7 +181,7 @@ fn find_file_start(blocks: &[Block], file_stop: usize) -> usize {
             Some(_) | None => break,
         }
     }
-    file_start
+    Ok(file_start)
 }
 
 fn find_free_space_stop(blocks: &[Block], free_space_start: usize) -> usize {
@@ -176,18 +201,62 @@ fn find_free_space_stop(blocks: &[Block], free_space_start: usize) -> usize {
 
     free_space_stop
 }
+// The same disk, kept as one span per file instead of one entry per block
+pub(crate) struct Disk {
+    files: Vec<Range<usize>>,
+    occupied: IntervalSet,
+}
+
+impl Disk {
+    pub fn parse(input: &str) -> Result<Disk, AocError> {
+        let mut files = Vec::new();
+        let mut position = 0;
+        for (index, ch) in input.trim_end().chars().enumerate() {
+            let n = parse_length(index, ch)?;
+            if index % 2 == 0 {
+                files.push(position..position + n);
+            }
+            position += n;
+        }
+        let occupied = files.iter().cloned().collect();
+        Ok(Disk { files, occupied })
+    }
+
+    pub fn compress_files(&mut self) -> Result<(), AocError> {
+        for file in self.files.iter_mut().rev() {
+            budget::step()?;
+            let length = file.len();
+            let Some(free_space_start) = self.occupied.first_gap(length, file.start) else {
+                continue;
+            };
+            self.occupied.remove(file.clone());
+            *file = free_space_start..free_space_start + length;
+            self.occupied.insert(file.clone());
+        }
+        Ok(())
+    }
+
+    pub fn checksum(&self) -> Result<usize, AocError> {
+        self.files.iter().enumerate().try_fold(0, |total, (id, file)| {
+            add(total, mul(id, sum(file.clone())?)?)
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_09_EASY, DAY_09_HARD};
-    use crate::day_09::{find_file_start, parse_input, Compress};
+    use crate::day_09::{find_file_start, parse_input, Block, Compress, Disk};
+    use crate::differential::{assert_agree, small_size};
+    use crate::generators;
 
     const EASY: &str = include_str!("../resources/day_09/easy.txt");
     const PREAMBLE: &str = include_str!("../resources/day_09/preamble.txt");
 
     #[test]
     fn preamble() {
-        let mut input = parse_input(PREAMBLE);
-        input.compress_blocks();
+        let mut input = parse_input(PREAMBLE).unwrap();
+        input.compress_blocks().unwrap();
         let left = input
             .into_iter()
             .enumerate()
@@ -198,8 +267,8 @@ mod tests {
 
     #[test]
     fn easy() {
-        let mut input = parse_input(EASY);
-        input.compress_blocks();
+        let mut input = parse_input(EASY).unwrap();
+        input.compress_blocks().unwrap();
         let left = input
             .into_iter()
             .enumerate()
@@ -210,8 +279,8 @@ mod tests {
     }
     #[test]
     fn preamble_hard() {
-        let mut input = parse_input(PREAMBLE);
-        input.compress_files();
+        let mut input = parse_input(PREAMBLE).unwrap();
+        input.compress_files().unwrap();
         let left = input
             .into_iter()
             .enumerate()
@@ -223,8 +292,8 @@ mod tests {
     }
     #[test]
     fn hard() {
-        let mut input = parse_input(EASY);
-        input.compress_files();
+        let mut input = parse_input(EASY).unwrap();
+        input.compress_files().unwrap();
         let left = input
             .into_iter()
             .enumerate()
@@ -234,4 +303,84 @@ mod tests {
         let right = DAY_09_HARD;
         assert_eq!(left, right)
     }
+
+    #[test]
+    fn preamble_spans() {
+        let mut disk = Disk::parse(PREAMBLE).unwrap();
+        disk.compress_files().unwrap();
+        let left = disk.checksum().unwrap();
+        let right = DAY_09_EASY;
+        assert_eq!(left, right)
+    }
+    #[test]
+    fn hard_spans() {
+        let mut disk = Disk::parse(EASY).unwrap();
+        disk.compress_files().unwrap();
+        let left = disk.checksum().unwrap();
+        let right = DAY_09_HARD;
+        assert_eq!(left, right)
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let with_newline = format!("{}\n", PREAMBLE);
+        assert_eq!(parse_input(&with_newline), parse_input(PREAMBLE));
+        assert_eq!(
+            Disk::parse(&with_newline).unwrap().checksum(),
+            Disk::parse(PREAMBLE).unwrap().checksum()
+        );
+        assert!(parse_input("12\n3").is_err());
+    }
+
+    // Moves whole files one block at a time, scanning the disk from the left for every file
+    fn reference_compress_files(input: &str) -> usize {
+        let mut blocks = Vec::new();
+        for (index, ch) in input.chars().enumerate() {
+            let length = ch.to_digit(10).unwrap() as usize;
+            let block = if index % 2 == 0 { Some(index / 2) } else { None };
+            blocks.extend(std::iter::repeat_n(block, length));
+        }
+        let files = input.len().div_ceil(2);
+        for id in (0..files).rev() {
+            let start = blocks.iter().position(|&block| block == Some(id)).unwrap();
+            let length = blocks.iter().filter(|&&block| block == Some(id)).count();
+            let free = (0..start).find(|&free| {
+                free + length <= start && blocks[free..free + length].iter().all(Block::is_none)
+            });
+            if let Some(free) = free {
+                for offset in 0..length {
+                    blocks.swap(free + offset, start + offset);
+                }
+            }
+        }
+        blocks
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, block)| block.map(|id| id * index))
+            .sum()
+    }
+
+    #[test]
+    fn matches_reference() {
+        assert_agree(
+            2000,
+            |seed| generators::disk_map(seed, small_size(seed, 12)),
+            |input| {
+                let mut blocks = parse_input(input).unwrap();
+                blocks.compress_files().unwrap();
+                let block_checksum = blocks
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(index, block)| block.map(|id| id * index))
+                    .sum::<usize>();
+                let mut disk = Disk::parse(input).unwrap();
+                disk.compress_files().unwrap();
+                (block_checksum, disk.checksum().unwrap())
+            },
+            |input| {
+                let checksum = reference_compress_files(input);
+                (checksum, checksum)
+            },
+        );
+    }
 }
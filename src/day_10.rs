@@ -1,3 +1,7 @@
+use crate::budget;
+use crate::error::AocError;
+use crate::memo::Memo;
+use crate::tile::rectangular;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 
@@ -48,18 +52,39 @@ impl<T> Matrix<T> for Vec<Vec<T>> {
         self.get(index.y).and_then(|row| row.get(index.x))
     }
 }
-pub fn find_score(terrain: &Vec<Vec<u32>>) -> usize{
+// One digit height per tile
+pub fn parse_input(input: &str) -> Result<Vec<Vec<u32>>, AocError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .map(|(x, ch)| {
+                    ch.to_digit(10).ok_or_else(|| {
+                        AocError::parse(format!("expected a height at ({}, {}), found {:?}", x, y, ch))
+                    })
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()
+        .and_then(rectangular)
+}
+
+pub fn find_score(terrain: &Vec<Vec<u32>>) -> Result<usize, AocError> {
     let trail_starts = get_trail_starts(terrain);
-    println!("{:?}", trail_starts);
     let trail_scores = trail_starts
         .iter()
-        .map(|start| find_trail_heads(start, terrain).len()).collect::<Vec<usize>>();
-    println!("{:?}", trail_scores);
-    trail_scores.iter().sum()
+        .map(|start| Ok(find_trail_heads(start, terrain)?.len()))
+        .collect::<Result<Vec<usize>, AocError>>()?;
+    Ok(trail_scores.iter().sum())
 
 }
 
-fn find_trail_heads(trail_start: &Point<usize>, terrain: &Vec<Vec<u32>>) -> HashSet<Point<usize>> {
+fn find_trail_heads(
+    trail_start: &Point<usize>,
+    terrain: &Vec<Vec<u32>>,
+) -> Result<HashSet<Point<usize>>, AocError> {
     let mut trail_heads = HashSet::new();
     let mut stack = Vec::new();
     stack.push(*trail_start);
@@ -67,7 +92,8 @@ fn find_trail_heads(trail_start: &Point<usize>, terrain: &Vec<Vec<u32>>) -> Hash
     while !stack.is_empty() {
         let mut new_stack = Vec::new();
         while let Some(current) = stack.pop() {
-            let original_value = terrain.get_scalar(&current).unwrap();
+            budget::step()?;
+            let original_value = terrain.get_scalar(&current).ok_or_else(off_the_map)?;
 
             if let Some(left) = current.shift(&Direction::West) {
                 if let Some(tile) = terrain.get_scalar(&left) {
@@ -121,7 +147,11 @@ fn find_trail_heads(trail_start: &Point<usize>, terrain: &Vec<Vec<u32>>) -> Hash
         stack = new_stack
     }
 
-    trail_heads
+    Ok(trail_heads)
+}
+
+fn off_the_map() -> AocError {
+    AocError::unreachable("a trail left the map")
 }
 
 fn get_trail_starts(terrain: &Vec<Vec<u32>>) -> HashSet<Point<usize>> {
@@ -138,120 +168,69 @@ fn get_trail_starts(terrain: &Vec<Vec<u32>>) -> HashSet<Point<usize>> {
     trail_heads
 }
 
-pub fn find_rating_score(terrain: &Vec<Vec<u32>>) -> usize{
+pub fn find_rating_score(terrain: &Vec<Vec<u32>>) -> Result<usize, AocError> {
     let trail_starts = get_trail_starts(terrain);
-    let trail_scores = trail_starts
-        .iter()
-        .map(|start| get_rating(start, terrain)).collect::<Vec<usize>>();
-    trail_scores.iter().sum()
-
+    let mut memo = Memo::new();
+    let rating = |recurse: &mut dyn FnMut(Point<usize>) -> Result<usize, AocError>,
+                  current: Point<usize>| get_rating(recurse, current, terrain);
+    trail_starts
+        .into_iter()
+        .map(|start| memo.get(start, &rating))
+        .sum()
 }
-fn get_rating(trail_start: &Point<usize>, terrain: &Vec<Vec<u32>>) -> usize {
-    let mut trail_heads = Vec::new();
-    let mut stack = Vec::new();
-    stack.push(*trail_start);
 
-    if trail_start == (&Point{x: 5, y: 5}) {
-        println!()
+// Number of distinct trails from `current` up to a 9, built from the ratings of the
+// neighbours one step higher
+fn get_rating(
+    recurse: &mut dyn FnMut(Point<usize>) -> Result<usize, AocError>,
+    current: Point<usize>,
+    terrain: &Vec<Vec<u32>>,
+) -> Result<usize, AocError> {
+    budget::step()?;
+    let original_value = terrain.get_scalar(&current).ok_or_else(off_the_map)?;
+    if *original_value == 9 {
+        return Ok(1);
     }
-    while !stack.is_empty() {
-        let mut new_stack = Vec::new();
-        while let Some(current) = stack.pop() {
-            let original_value = terrain.get_scalar(&current).unwrap();
-
-            if let Some(left) = current.shift(&Direction::West) {
-                if let Some(tile) = terrain.get_scalar(&left) {
-                    if original_value + 1 == *tile {
-                        if *tile == 9 {
-                            trail_heads.push(left);
-                        } else {
-                            new_stack.push(left);
-                        }
-
-                    }
-                }
-            };
-            if let Some(right) = current.shift(&Direction::East) {
-                if let Some(tile) = terrain.get_scalar(&right) {
-                    if original_value + 1 == *tile {
-                        if *tile == 9 {
-                            trail_heads.push(right);
-                        } else {
-                            new_stack.push(right);
-                        }
-
-                    }
-                }
-            };
-            if let Some(bottom) = current.shift(&Direction::South) {
-                if let Some(tile) = terrain.get_scalar(&bottom) {
-                    if original_value + 1 == *tile {
-                        if *tile == 9 {
-                            trail_heads.push(bottom);
-                        } else {
-                            new_stack.push(bottom);
-                        }
-
-                    }
-                }
-            }
-            if let Some(top) = current.shift(&Direction::North) {
-                if let Some(tile) = terrain.get_scalar(&top) {
-                    if original_value + 1 == *tile {
-                        if *tile == 9 {
-                            trail_heads.push(top);
-                        } else {
-                            new_stack.push(top);
-                        }
-
-                    }
-                }
-            }
-        }
-        stack = new_stack
-    }
-
-    trail_heads.len()
+    [Direction::West, Direction::East, Direction::South, Direction::North]
+        .iter()
+        .filter_map(|direction| current.shift(direction))
+        .filter(|next| terrain.get_scalar(next) == Some(&(original_value + 1)))
+        .map(recurse)
+        .sum()
 }
 #[cfg(test)]
 mod test {
     use crate::answers::{DAY_10_EASY, DAY_10_HARD};
-    use crate::day_10::{find_rating_score, find_score};
+    use crate::day_10::{find_rating_score, find_score, parse_input};
 
     const PREAMBLE: &str = include_str!("../resources/day_10/preamble.txt");
     const EASY: &str = include_str!("../resources/day_10/easy.txt");
 
-    fn parse_input(input: &str) -> Vec<Vec<u32>> {
-        input
-            .lines()
-            .map(|line| line.chars().map(|ch| ch.to_digit(10).unwrap()).collect())
-            .collect()
-    }
     #[test]
     fn preamble() {
-        let input = parse_input(PREAMBLE);
-        let left = find_score(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = find_score(&input).unwrap();
         let right = 36;
         assert_eq!(left, right);
     }
     #[test]
     fn easy() {
-        let input = parse_input(EASY);
-        let left = find_score(&input);
+        let input = parse_input(EASY).unwrap();
+        let left = find_score(&input).unwrap();
         let right = DAY_10_EASY;
         assert_eq!(left, right);
     }
     #[test]
     fn preamble_hard() {
-        let input = parse_input(PREAMBLE);
-        let left = find_rating_score(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = find_rating_score(&input).unwrap();
         let right = 81;
         assert_eq!(left, right);
     }
     #[test]
     fn hard() {
-        let input = parse_input(EASY);
-        let left = find_rating_score(&input);
+        let input = parse_input(EASY).unwrap();
+        let left = find_rating_score(&input).unwrap();
         let right = DAY_10_HARD;
         assert_eq!(left, right);
     }
@@ -1,72 +1,81 @@
-use std::collections::HashMap;
-use std::hash::Hash;
+use crate::arith::{add, mul};
+use crate::budget;
+use crate::error::AocError;
+use crate::memo::Memo;
 
 type Stones = Vec<isize>;
 
-fn blink_stone(stone: usize) -> Vec<usize> {
+// The engravings on the stones, separated by spaces
+pub fn parse_input(input: &str) -> Result<Vec<usize>, AocError> {
+    input.split_whitespace().map(|stone| Ok(stone.parse()?)).collect()
+}
+
+fn blink_stone(stone: usize) -> Result<Vec<usize>, AocError> {
     if stone == 0 {
-        return vec![1];
+        return Ok(vec![1]);
     };
     let as_string = stone.to_string();
     let length = as_string.len();
     if (length % 2) == 0 {
         let (left, right) = as_string.split_at(length / 2);
-        vec![left.parse().unwrap(), right.parse().unwrap()]
+        Ok(vec![left.parse()?, right.parse()?])
     } else {
-        vec![stone * 2024]
+        Ok(vec![mul(stone, 2024)?])
     }
 }
 
-fn blink(stones: HashMap<usize, usize>) -> HashMap<usize, usize> {
-    let mut new_stones = HashMap::new();
-    for (stone, count) in stones {
-        let expanded = blink_stone(stone);
-        for new_stone in expanded {
-            let old_count = new_stones.entry(new_stone).or_insert(0);
-            let old_count = old_count.clone();
-            new_stones.insert(new_stone, old_count + count);
-        }
-    }
-    new_stones
-}
+type StoneCount = Result<usize, AocError>;
 
-fn blink_stones(stones: Vec<usize>, n: usize) -> usize {
-    let mut new_stones = HashMap::new();
-    stones.into_iter().for_each(|stone| {
-        let old_count = new_stones.entry(stone).or_insert(0);
-        let old_count = old_count.clone();
-        new_stones.insert(stone, old_count + 1);
-    });
-    for i in 0..n {
-        new_stones = blink(new_stones);
+// Number of stones a single stone turns into after `blinks` blinks
+fn count_stones(
+    recurse: &mut dyn FnMut((usize, usize)) -> StoneCount,
+    (stone, blinks): (usize, usize),
+) -> StoneCount {
+    budget::step()?;
+    if blinks == 0 {
+        return Ok(1);
     }
-
-    new_stones.values().sum()
+    blink_stone(stone)?
+        .into_iter()
+        .try_fold(0, |total, new_stone| add(total, recurse((new_stone, blinks - 1))?))
 }
 
+pub fn blink_stones(stones: Vec<usize>, n: usize) -> Result<usize, AocError> {
+    let mut memo = Memo::new();
+    stones
+        .into_iter()
+        .try_fold(0, |total, stone| add(total, memo.get((stone, n), &count_stones)?))
+}
 
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_11_EASY, DAY_11_HARD};
-    use crate::day_11::blink_stones;
+    use crate::day_11::{blink_stones, parse_input};
 
 
     #[test]
     fn preamble() {
-        let left = blink_stones(vec![125, 17], 25);
+        let left = blink_stones(parse_input("125 17").unwrap(), 25).unwrap();
         let right = 55312;
         assert_eq!(left, right)
     }
     #[test]
     fn easy() {
-        let left = blink_stones(vec![27, 10647, 103, 9, 0, 5524, 4594227, 902936], 25);
+        let left = blink_stones(vec![27, 10647, 103, 9, 0, 5524, 4594227, 902936], 25).unwrap();
         let right = DAY_11_EASY;
         assert_eq!(left, right)
     }
     #[test]
     fn hard() {
-        let left = blink_stones(vec![27, 10647, 103, 9, 0, 5524, 4594227, 902936], 75);
+        let left = blink_stones(vec![27, 10647, 103, 9, 0, 5524, 4594227, 902936], 75).unwrap();
         let right = DAY_11_HARD;
         assert_eq!(left, right)
     }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn overflow() {
+        let left = blink_stones(vec![usize::MAX / 1000], 1);
+        assert_eq!(left, Err(crate::error::AocError::Overflow));
+    }
 }
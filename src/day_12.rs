@@ -1,8 +1,18 @@
+use crate::arith::{add, mul};
+use crate::bitset::{DirectionalGridSet, GridSet};
+use crate::budget;
 use crate::convex_hull::{find_convex_hull, get_bottom_left};
+use crate::error::AocError;
 use crate::graph::{Coordinate, Direction, Matrix, Point};
-use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::tile::rectangular;
+use crate::union_find::GridRegions;
+use std::collections::BTreeSet;
 use clap::Parser;
 
+fn off_the_land() -> AocError {
+    AocError::unreachable("a region reached off the land")
+}
+
 fn iter_directions() -> [Direction; 4] {
     [
         Direction::North,
@@ -12,108 +22,86 @@ fn iter_directions() -> [Direction; 4] {
     ]
 }
 
-fn get_price(land: &Vec<Vec<char>>) -> usize {
-    let mut visited = HashSet::new();
-    let mut price = 0;
-    for row in 0..land.len() {
-        for column in 0..land[0].len() {
-            let current = Point { x: column, y: row }.try_into().unwrap();
-
-            if visited.contains(&current) {
-                continue;
-            }
+pub fn parse_input(input: &str) -> Result<Vec<Vec<char>>, AocError> {
+    rectangular(input.lines().map(|line| line.chars().collect()).collect())
+}
 
-            let region = find_region2(&current, land);
-            match region {
-                None => continue,
-                Some(Region {
-                    coordinates,
-                    area,
-                    perimeter,
-                    ..
-                }) => {
-                    visited.extend(coordinates);
-                    price += area * perimeter;
-                }
-            }
-        }
-    }
-    price
+pub fn get_price(land: &Vec<Vec<char>>) -> Result<usize, AocError> {
+    GridRegions::new(land).price()
 }
 
-fn get_discounted_price(land: &Vec<Vec<char>>) -> usize {
-    let mut visited = HashSet::new();
+pub fn get_discounted_price(land: &Vec<Vec<char>>) -> Result<usize, AocError> {
+    let mut visited = GridSet::for_grid(land);
+    // the fence of the region being walked, empty again once its sides are counted
+    let mut fence = DirectionalGridSet::for_grid(land);
     let mut price = 0;
     for row in 0..land.len() {
-        for column in 0..land[0].len() {
-            let current = Point { x: column, y: row }.try_into().unwrap();
+        for column in 0..land[row].len() {
+            let current = Point { x: column, y: row }
+                .try_into()
+                .map_err(|_| AocError::Overflow)?;
 
             if visited.contains(&current) {
                 continue;
             }
 
-            let region = find_region2(&current, land);
-            match region {
-                None => continue,
-                Some(Region {
-                         coordinates,
-                         area,
-                         n_sides,
-                         ..
-                     }) => {
-                    visited.extend(coordinates);
-                    price += area * n_sides;
-                }
-            }
+            let Region { area, n_sides, .. } =
+                find_region2(&current, land, &mut visited, &mut fence)?;
+            price = add(price, mul(area, n_sides)?)?;
         }
     }
-    price
+    Ok(price)
 }
-fn get_regions(land: &Vec<Vec<char>>) -> Vec<Region> {
-    let mut visited = HashSet::new();
+fn get_regions(land: &Vec<Vec<char>>) -> Result<Vec<Region>, AocError> {
+    let mut visited = GridSet::for_grid(land);
     let mut regions = Vec::new();
 
     for row in 0..land.len() {
         for column in 0..land[0].len() {
-            let current = Point { x: column, y: row }.try_into().unwrap();
+            let current = Point { x: column, y: row }
+                .try_into()
+                .map_err(|_| AocError::Overflow)?;
             if visited.contains(&current) {
                 continue;
             }
-            let region = find_region(&current, land);
-            match region {
-                None => continue,
-                Some(region) => {
-                    visited.extend(region.coordinates.clone());
-                    regions.push(region);
-                }
-            }
+            regions.push(find_region(&current, land, &mut visited)?);
         }
     }
-    regions
+    Ok(regions)
 }
 struct Region {
     tile: char,
-    coordinates: HashSet<Point<isize>>,
+    cells: Vec<Coordinate>,
     area: usize,
     perimeter: usize,
     n_sides: usize,
 }
 
-fn find_region(start: &Coordinate, land: &Vec<Vec<char>>) -> Option<Region> {
+// Marks the region's cells in visited as it finds them, so a neighbour already visited either
+// belongs to this region or grows a different plant.
+fn find_region(
+    start: &Coordinate,
+    land: &Vec<Vec<char>>,
+    visited: &mut GridSet,
+) -> Result<Region, AocError> {
     // Walk and flood fill, adding 1 whenever a side does not connect and adding 1 whenever we find a value
 
-    let mut area = 0;
     let mut perimeter = 0;
 
     let mut stack = Vec::new();
-    let mut coordinates = HashSet::new();
+    let mut cells = Vec::new();
 
-    let first = land.get_scalar(&start.try_into().unwrap())?;
+    let first = start
+        .try_into()
+        .ok()
+        .and_then(|start| land.get_scalar(&start))
+        .ok_or_else(off_the_land)?;
     stack.push(start.clone());
+    visited.insert(start).ok_or_else(off_the_land)?;
 
     while let Some(current) = stack.pop() {
-        area += 1;
-        coordinates.insert(current.clone());
+        budget::step()?;
+        cells.push(current);
 
         for direction in iter_directions() {
 
@@ -130,47 +118,54 @@ fn find_region(start: &Coordinate, land: &Vec<Vec<char>>) -> Option<Region> {
                 }
             };
 
-            if coordinates.contains(&next) || stack.contains(&next) {
-                continue;
-            }
-
             let Some(tile) = land.get_scalar(&index) else {
                 perimeter += 1;
                 continue;
             };
 
-            if tile == first {
-                stack.push(next);
-            } else {
+            if tile != first {
                 perimeter += 1;
+            } else if visited.insert(&next).ok_or_else(off_the_land)? {
+                stack.push(next);
             }
         }
     }
-    Some(Region {
+    Ok(Region {
         tile: *first,
-        coordinates,
-        area,
+        area: cells.len(),
+        cells,
         perimeter,
         n_sides: 0
     })
 }
 
 
-fn find_region2(start: &Coordinate, land: &Vec<Vec<char>>) -> Option<Region> {
+// As find_region, also counting the sides. Each fence edge is put in fence as it's found, then
+// taking out an edge takes out the rest of its side with it.
+fn find_region2(
+    start: &Coordinate,
+    land: &Vec<Vec<char>>,
+    visited: &mut GridSet,
+    fence: &mut DirectionalGridSet<Direction>,
+) -> Result<Region, AocError> {
     // Walk and flood fill, adding 1 whenever a side does not connect and adding 1 whenever we find a value
 
-    let mut area = 0;
     let mut perimeter = Vec::new();
 
     let mut stack = Vec::new();
-    let mut coordinates = HashSet::new();
+    let mut cells = Vec::new();
 
-    let first = land.get_scalar(&start.try_into().unwrap())?;
+    let first = start
+        .try_into()
+        .ok()
+        .and_then(|start| land.get_scalar(&start))
+        .ok_or_else(off_the_land)?;
     stack.push(start.clone());
+    visited.insert(start).ok_or_else(off_the_land)?;
 
     while let Some(current) = stack.pop() {
-        area += 1;
-        coordinates.insert(current.clone());
+        budget::step()?;
+        cells.push(current);
 
         for direction in iter_directions() {
 
@@ -189,131 +184,174 @@ fn find_region2(start: &Coordinate, land: &Vec<Vec<char>>) -> Option<Region> {
                 }
             };
 
-            if coordinates.contains(&next) || stack.contains(&next) {
-                continue;
-            }
-
             let Some(tile) = land.get_scalar(&index) else {
                 let side = (current, direction);
                 perimeter.push(side);
                 continue;
             };
 
-            if tile == first {
-                stack.push(next);
-            } else {
+            if tile != first {
                 let side = (current, direction);
                 perimeter.push(side);
+            } else if visited.insert(&next).ok_or_else(off_the_land)? {
+                stack.push(next);
             }
         }
     }
 
     let perimeter_length = perimeter.len();
-    // convert to a hash and start popping
+    for (current, direction) in &perimeter {
+        fence.insert(direction, current).ok_or_else(off_the_land)?;
+    }
 
     let mut n_sides = 0;
-    let mut perimeter = perimeter.into_iter().collect::<HashSet<_>>();
 
-    loop {
-        // horrible but rust sucks at this part
-        let Some(next) = perimeter.iter().next() else {
-            break
-        };
-        let next = next.clone();
-        let (current, direction)= perimeter.take(&next).unwrap();
+    for (current, direction) in perimeter {
+        budget::step()?;
+        if !fence.remove(&direction, &current) {
+            // already counted as part of a side
+            continue;
+        }
 
         match direction {
             direction @ (Direction::North | Direction::South)=> {
-                let mut left = (current.shift_west().unwrap(), direction.clone());
-                while perimeter.remove(&left) {
-                    left = (left.0.shift_west().unwrap(), left.1);
+                let mut left = current.shift_west().ok_or_else(off_the_land)?;
+                while fence.remove(&direction, &left) {
+                    left = left.shift_west().ok_or_else(off_the_land)?;
                 }
-                let mut right = (current.shift_east().unwrap(), direction.clone());
-                while perimeter.remove(&right) {
-                    right = (right.0.shift_east().unwrap(), right.1);
+                let mut right = current.shift_east().ok_or_else(off_the_land)?;
+                while fence.remove(&direction, &right) {
+                    right = right.shift_east().ok_or_else(off_the_land)?;
                 }
                 n_sides += 1;
             },
             direction @(Direction::West| Direction::East) => {
-                let mut top = (current.shift_north().unwrap(), direction.clone());
-                while perimeter.remove(&top) {
-                    top = (top.0.shift_north().unwrap(), top.1);
+                let mut top = current.shift_north().ok_or_else(off_the_land)?;
+                while fence.remove(&direction, &top) {
+                    top = top.shift_north().ok_or_else(off_the_land)?;
                 }
-                let mut bottom = (current.shift_south().unwrap(), direction.clone());
-                while perimeter.remove(&bottom) {
-                    bottom = (bottom.0.shift_south().unwrap(), bottom.1);
+                let mut bottom = current.shift_south().ok_or_else(off_the_land)?;
+                while fence.remove(&direction, &bottom) {
+                    bottom = bottom.shift_south().ok_or_else(off_the_land)?;
                 }
                 n_sides += 1;
             }
-            _ => unreachable!()
+            direction => {
+                return Err(AocError::unreachable(format!("a fence facing {:?}", direction)))
+            }
         }
     }
 
-    // how do i get the adjacent
-    Some(Region {
+    Ok(Region {
         tile: *first,
-        coordinates,
-        area,
+        area: cells.len(),
+        cells,
         perimeter: perimeter_length,
         n_sides:n_sides
     })
 }
 
-fn find_neighbours(
-    current: &Point<isize>,
-    coordinates: &HashSet<Point<isize>>,
-) -> HashMap<Direction, Point<isize>> {
-    let mut neighbours = HashMap::new();
-    for direction in iter_directions() {
-        if let Some(next) = current.shift(&direction) {
-            if coordinates.contains(&next) {
-                neighbours.insert(direction, next);
-            }
-        }
-    }
-    neighbours
-}
-
 
 
 #[cfg(test)]
 mod tests {
     use crate::answers::{DAY_11_EASY, DAY_12_EASY, DAY_12_HARD};
-    use crate::day_12::{get_discounted_price, get_price};
+    use crate::day_12::{get_discounted_price, get_price, parse_input};
+    use crate::differential::{assert_agree, small_size};
+    use crate::generators;
+    use std::collections::HashSet;
 
     const PREAMBLE: &str = include_str!("../resources/day_12/preamble.txt");
     const INPUT: &str = include_str!("../resources/day_12/input.txt");
 
-    fn parse_input(input: &str) -> Vec<Vec<char>> {
-        input.lines().map(|line| line.chars().collect()).collect()
-    }
-
     #[test]
     fn preamble() {
-        let input = parse_input(PREAMBLE);
-        let left = get_price(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = get_price(&input).unwrap();
         let right = 1930;
         assert_eq!(left, right)
     }
     #[test]
     fn easy() {
-        let input = parse_input(INPUT);
-        let left = get_price(&input);
+        let input = parse_input(INPUT).unwrap();
+        let left = get_price(&input).unwrap();
         let right = DAY_12_EASY;
         assert_eq!(left, right)
     }
     #[test]
     fn preamble_hard() {
-        let input = parse_input(PREAMBLE);
-        let left = get_discounted_price(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = get_discounted_price(&input).unwrap();
         let right =DAY_12_HARD;
         assert_eq!(left, right)
     }
     #[test]
     fn hard() {
-        let input = parse_input(INPUT);
-        let left = get_discounted_price(&input);
+        let input = parse_input(INPUT).unwrap();
+        let left = get_discounted_price(&input).unwrap();
         let right =923480;
         assert_eq!(left, right)
     }
+
+    // Flood fills every region and counts its fence edges one by one. A side is counted at the
+    // edge that has no matching edge on the cell before it along the side.
+    fn reference_prices(land: &[Vec<char>]) -> (usize, usize) {
+        const STEPS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+        let plant = |(x, y): (isize, isize)| {
+            land.get(y as usize).and_then(|row| row.get(x as usize)).copied()
+        };
+        let mut seen = HashSet::new();
+        let (mut price, mut discounted_price) = (0, 0);
+        for y in 0..land.len() as isize {
+            for x in 0..land[y as usize].len() as isize {
+                if !seen.insert((x, y)) {
+                    continue;
+                }
+                let kind = plant((x, y));
+                let mut region = vec![(x, y)];
+                let mut next = 0;
+                while next < region.len() {
+                    let (x, y) = region[next];
+                    next += 1;
+                    for (dx, dy) in STEPS {
+                        let neighbour = (x + dx, y + dy);
+                        if plant(neighbour) == kind && seen.insert(neighbour) {
+                            region.push(neighbour);
+                        }
+                    }
+                }
+                let fenced = |(x, y): (isize, isize), (dx, dy): (isize, isize)| {
+                    plant((x, y)) == kind && plant((x + dx, y + dy)) != kind
+                };
+                let (mut perimeter, mut sides) = (0, 0);
+                for &(x, y) in &region {
+                    for (dx, dy) in STEPS {
+                        if fenced((x, y), (dx, dy)) {
+                            perimeter += 1;
+                            // the cell before this one along the fence
+                            if !fenced((x + dy, y - dx), (dx, dy)) {
+                                sides += 1;
+                            }
+                        }
+                    }
+                }
+                price += region.len() * perimeter;
+                discounted_price += region.len() * sides;
+            }
+        }
+        (price, discounted_price)
+    }
+
+    #[test]
+    fn matches_reference() {
+        assert_agree(
+            1000,
+            |seed| generators::garden(seed, small_size(seed, 10)),
+            |input| {
+                let land = parse_input(input).unwrap();
+                (get_price(&land).unwrap(), get_discounted_price(&land).unwrap())
+            },
+            |input| reference_prices(&parse_input(input).unwrap()),
+        );
+    }
 }
@@ -1,18 +1,72 @@
-use crate::graph::{Coordinate, Point};
-struct Button {
-    x: f64,
-    y: f64,
+use crate::arith::sum;
+use crate::budget;
+use crate::error::AocError;
+use crate::graph::Point;
+use crate::linear_system::{solve, Solution};
+use crate::number_theory::extended_gcd;
+use crate::parallel::Parallelism;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub(crate) struct Button {
+    x: i128,
+    y: i128,
 }
 
-type Prize = Point<f64>;
+type Prize = Point<i128>;
 
 
-struct Trial {
+pub(crate) struct Trial {
     button_a: Button,
     button_b: Button,
     prize: Prize,
 }
 
+// Claw machines are three lines each, with a blank line between machines
+pub fn parse_input(input: &str) -> Result<Vec<Trial>, AocError> {
+    input
+        .split("\n\n")
+        .map(|trial| {
+            let mut lines = trial.lines();
+            let mut next_line = || {
+                lines
+                    .next()
+                    .ok_or_else(|| AocError::parse(format!("incomplete claw machine {:?}", trial)))
+            };
+            let button_a = parse_button_line(next_line()?)?;
+            let button_b = parse_button_line(next_line()?)?;
+            let prize = parse_prize_line(next_line()?)?;
+            Ok(Trial { button_a, button_b, prize })
+        })
+        .collect()
+}
+
+fn parse_button_line(line: &str) -> Result<Button, AocError> {
+    lazy_static! {
+        static ref BUTTON: Regex = Regex::new(r"^Button (A|B): X\+(?<x>\d+), Y\+(?<y>\d+)$").unwrap();
+    };
+    let captures = BUTTON
+        .captures(line)
+        .ok_or_else(|| AocError::parse(format!("invalid button {:?}", line)))?;
+    Ok(Button {
+        x: captures["x"].parse()?,
+        y: captures["y"].parse()?,
+    })
+}
+
+fn parse_prize_line(line: &str) -> Result<Prize, AocError> {
+    lazy_static! {
+        static ref PRIZE: Regex = Regex::new(r"^Prize: X=(?<x>\d+), Y=(?<y>\d+)$").unwrap();
+    };
+    let captures = PRIZE
+        .captures(line)
+        .ok_or_else(|| AocError::parse(format!("invalid prize {:?}", line)))?;
+    Ok(Prize {
+        x: captures["x"].parse()?,
+        y: captures["y"].parse()?,
+    })
+}
+
 // Apparently this is simultaneous equations
 /*
 Button A: X+94, Y+34
@@ -21,99 +75,138 @@ Prize: X=8400, Y=5400
 
 
 */
-fn get_cost(trial: &Trial) -> isize{
+fn get_cost(trial: &Trial) -> Result<isize, AocError> {
+    budget::step()?;
     let Prize {x: px, y: py} = trial.prize;
     let Button {x: ax, y: ay} = trial.button_a;
     let Button {x: bx, y: by} = trial.button_b;
 
-    let an = (px*by - py*bx) / (ax*by - ay*bx);
-    let bn = (px-ax * an) / bx;
+    let presses = solve(&[vec![ax, bx], vec![ay, by]], &[px, py])?;
+    let cost = match presses {
+        Solution::Unique(presses) => {
+            let (Some(an), Some(bn)) = (presses[0].to_integer(), presses[1].to_integer()) else {
+                return Ok(0)
+            };
+            if an < 0 || bn < 0 {
+                return Ok(0)
+            }
+            an.checked_mul(3)
+                .and_then(|a_cost| a_cost.checked_add(bn))
+                .ok_or(AocError::Overflow)?
+        }
+        Solution::None => return Ok(0),
+        // both buttons move along the same line, so either equation that has a button moving
+        // along it says everything the other does
+        Solution::Infinite { .. } => {
+            let cheapest = if ax != 0 || bx != 0 {
+                cheapest_on_line(ax, bx, px)
+            } else {
+                cheapest_on_line(ay, by, py)
+            };
+            match cheapest {
+                Some(cost) => cost,
+                None => return Ok(0),
+            }
+        }
+    };
+    cost.try_into().map_err(|_| AocError::Overflow)
+}
 
-    if (an.fract() > 0. || bn.fract() > 0.) {
-       return 0
+// Cheapest non-negative a, b with a * ax + b * bx = px, where a costs 3 and b costs 1
+fn cheapest_on_line(ax: i128, bx: i128, px: i128) -> Option<i128> {
+    if ax == 0 && bx == 0 {
+        return (px == 0).then_some(0);
+    }
+    let (divisor, x, y) = extended_gcd(ax, bx)?;
+    if px % divisor != 0 {
+        return None;
+    }
+    let scale = px / divisor;
+    let (a0, b0) = (x.checked_mul(scale)?, y.checked_mul(scale)?);
+    // a = a0 + k * a_step, b = b0 - k * b_step for any integer k
+    let (a_step, b_step) = (bx / divisor, ax / divisor);
+    let (a_low, a_high) = non_negative(a0, a_step)?;
+    let (b_low, b_high) = non_negative(b0, b_step.checked_neg()?)?;
+    let (k_min, k_max) = (a_low.max(b_low), a_high.min(b_high));
+    if k_min > k_max {
+        return None;
+    }
+    // the cost is linear in k, so the cheapest end of the range wins. It can't fall forever
+    // with both presses non-negative, so that end is never an unbounded one.
+    let slope = a_step.checked_mul(3)?.checked_sub(b_step)?;
+    let k = if slope >= 0 { k_min } else { k_max };
+    if k == i128::MIN || k == i128::MAX {
+        return None;
     }
-    let an = an as isize;
-    let bn = bn as isize;
-    an * 3 + bn
+    let an = a0.checked_add(k.checked_mul(a_step)?)?;
+    let bn = b0.checked_sub(k.checked_mul(b_step)?)?;
+    an.checked_mul(3)?.checked_add(bn)
 }
 
-fn fewest_tokens(trials: &Vec<Trial>) -> isize{
-    trials.iter().map(get_cost).sum()
+// The ks with start + k * step >= 0, as an inclusive range that runs to i128::MIN or i128::MAX
+// at an unbounded end
+fn non_negative(start: i128, step: i128) -> Option<(i128, i128)> {
+    match step.signum() {
+        0 => (start >= 0).then_some((i128::MIN, i128::MAX)),
+        1 => {
+            let below = start.checked_neg()?;
+            let k_min = below.div_euclid(step) + (below.rem_euclid(step) != 0) as i128;
+            Some((k_min, i128::MAX))
+        }
+        _ => Some((i128::MIN, start.div_euclid(step.checked_neg()?))),
+    }
+}
+
+// Part two's unit conversion puts every prize this much further along both axes
+pub const PRIZE_OFFSET: i128 = 10_000_000_000_000;
+
+pub fn move_prizes(trials: &mut [Trial], offset: i128) -> Result<(), AocError> {
+    for trial in trials {
+        trial.prize.x = trial.prize.x.checked_add(offset).ok_or(AocError::Overflow)?;
+        trial.prize.y = trial.prize.y.checked_add(offset).ok_or(AocError::Overflow)?;
+    }
+    Ok(())
+}
+
+pub fn fewest_tokens(trials: &Vec<Trial>) -> Result<isize, AocError> {
+    fewest_tokens_with(trials, Parallelism::sequential())
+}
+
+pub fn fewest_tokens_with(trials: &[Trial], parallelism: Parallelism) -> Result<isize, AocError> {
+    let costs = parallelism.map(trials, get_cost).into_iter().collect::<Result<Vec<_>, _>>()?;
+    sum(costs)
 }
 
 #[cfg(test)]
 mod tests {
-    use regex::Regex;
-    use lazy_static::lazy_static;
     use crate::answers::{DAY_13_EASY, DAY_13_HARD};
-    use crate::day_13::{fewest_tokens, Button, Prize, Trial};
+    use crate::day_13::{
+        cheapest_on_line, fewest_tokens, fewest_tokens_with, get_cost, move_prizes, parse_input,
+        Button, Prize, Trial, PRIZE_OFFSET,
+    };
+    use crate::differential::{assert_agree, small_size};
+    use crate::generators::{self, Rng};
+    use crate::parallel::Parallelism;
     const PREAMBLE: &str = include_str!("../resources/day_13/preamble.txt");
     const EASY: &str = include_str!("../resources/day_13/easy.txt");
 
-    fn parse_input(input: &str) -> Vec<Trial> {
-
-        input.split("\n\n").map(|trial| {
-            let mut lines = trial.lines();
-            let button_a = parse_button_line(lines.next().unwrap());
-            let button_b = parse_button_line(lines.next().unwrap());
-            let prize = parse_prize_line(lines.next().unwrap());
-            Trial {
-                button_a,
-                button_b ,prize
-            }
-        }).collect()
-    }
-
     fn parse_hard_input(input: &str) -> Vec<Trial> {
-
-        input.split("\n\n").map(|trial| {
-            let mut lines = trial.lines();
-            let button_a = parse_button_line(lines.next().unwrap());
-            let button_b = parse_button_line(lines.next().unwrap());
-            let mut prize = parse_prize_line(lines.next().unwrap());
-            prize.x += 1_000_000_000_000_0.;
-            prize.y += 1_000_000_000_000_0.;
-
-            Trial {
-                button_a,
-                button_b ,prize
-            }
-        }).collect()
-    }
-    fn parse_button_line(button_line: &str) -> Button {
-        lazy_static! {
-            static ref BUTTON: regex::Regex = Regex::new(r"^Button (A|B): X\+(?<x>\d+), Y\+(?<y>\d+)$").unwrap();
-        };
-
-
-
-        let captures = BUTTON.captures(button_line).unwrap();
-        let x_increment = captures.name("x").unwrap().as_str().parse().unwrap();
-        let y_increment = captures.name("y").unwrap().as_str().parse().unwrap();
-        Button{ x: x_increment, y: y_increment }
-    }
-
-    fn parse_prize_line(line: &str) -> Prize {
-        lazy_static! {
-            static ref PRIZE: regex::Regex = Regex::new(r"^Prize: X=(?<x>\d+), Y=(?<y>\d+)$").unwrap();
-        };
-        let captures = PRIZE.captures(line).unwrap();
-        let x = captures.name("x").unwrap().as_str().parse().unwrap();
-        let y = captures.name("y").unwrap().as_str().parse().unwrap();
-        Prize{x, y}
+        let mut trials = parse_input(input).unwrap();
+        move_prizes(&mut trials, PRIZE_OFFSET).unwrap();
+        trials
     }
 
     #[test]
     fn preamble() {
-        let input = parse_input(PREAMBLE);
-        let left = fewest_tokens(&input);
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = fewest_tokens(&input).unwrap();
         let right = 480;
         assert_eq!(left, right)
     }
     #[test]
     fn easy() {
-        let input = parse_input(EASY);
-        let left = fewest_tokens(&input);
+        let input = parse_input(EASY).unwrap();
+        let left = fewest_tokens(&input).unwrap();
         let right = DAY_13_EASY;
         assert_eq!(left, right)
     }
@@ -121,8 +214,139 @@ mod tests {
     #[test]
     fn hard() {
         let input = parse_hard_input(EASY);
-        let left = fewest_tokens(&input);
+        let left = fewest_tokens(&input).unwrap();
         let right = DAY_13_HARD;
         assert_eq!(left, right)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn collinear_buttons() {
+        let trial = Trial {
+            button_a: Button { x: 6, y: 3 },
+            button_b: Button { x: 2, y: 1 },
+            prize: Prize { x: 20, y: 10 },
+        };
+        // 10 presses of B beat any mix with A, which is three times as expensive for the same distance
+        assert_eq!(get_cost(&trial), Ok(10));
+        assert_eq!(cheapest_on_line(6, 2, 20), Some(10));
+        assert_eq!(cheapest_on_line(9, 2, 20), Some(7));
+        assert_eq!(cheapest_on_line(4, 6, 7), None);
+        assert_eq!(cheapest_on_line(-6, -2, -20), Some(10));
+        assert_eq!(cheapest_on_line(3, -1, 2), Some(4));
+        assert_eq!(cheapest_on_line(0, 2, 6), Some(3));
+        assert_eq!(cheapest_on_line(0, 0, 0), Some(0));
+        assert_eq!(cheapest_on_line(0, 0, 5), None);
+
+        // neither button moves along x, so it's the y equation that decides
+        let trial = Trial {
+            button_a: Button { x: 0, y: 3 },
+            button_b: Button { x: 0, y: 2 },
+            prize: Prize { x: 0, y: 7 },
+        };
+        assert_eq!(get_cost(&trial), Ok(5));
+    }
+
+    // Tries every number of A presses that doesn't overshoot and works out B from the rest
+    fn reference_tokens(trials: &[Trial]) -> isize {
+        let mut tokens = 0;
+        for Trial { button_a: a, button_b: b, prize } in trials {
+            let cheapest = (0..=prize.x / a.x)
+                .filter_map(|presses_a| {
+                    let rest = prize.x - presses_a * a.x;
+                    let presses_b = rest / b.x;
+                    let hits = rest % b.x == 0 && presses_a * a.y + presses_b * b.y == prize.y;
+                    hits.then_some(3 * presses_a + presses_b)
+                })
+                .min();
+            tokens += cheapest.unwrap_or(0);
+        }
+        tokens as isize
+    }
+
+    #[test]
+    fn matches_reference() {
+        assert_agree(
+            1000,
+            |seed| generators::claw_machines(seed, small_size(seed, 5)),
+            |input| fewest_tokens(&parse_input(input).unwrap()).unwrap(),
+            |input| reference_tokens(&parse_input(input).unwrap()),
+        );
+    }
+
+    #[test]
+    fn matches_reference_collinear() {
+        // random buttons are almost never collinear, so build machines where they are
+        let machine = |seed: u64| {
+            let mut rng = Rng::new(seed);
+            let (x, y) = (rng.between(1, 9), rng.between(1, 9));
+            let (scale_a, scale_b) = (rng.between(1, 5), rng.between(1, 5));
+            let presses = rng.between(0, 40);
+            let offset = rng.between(0, 2);
+            format!(
+                "Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}",
+                scale_a * x,
+                scale_a * y,
+                scale_b * x,
+                scale_b * y,
+                presses * x + offset,
+                presses * y
+            )
+        };
+        assert_agree(
+            1000,
+            machine,
+            |input| fewest_tokens(&parse_input(input).unwrap()).unwrap(),
+            |input| reference_tokens(&parse_input(input).unwrap()),
+        );
+    }
+
+    #[test]
+    fn matches_reference_vertical() {
+        let machine = |seed: u64| {
+            let mut rng = Rng::new(seed);
+            let (a, b) = (rng.between(1, 9), rng.between(1, 9));
+            let prize = a * rng.between(0, 20) + b * rng.between(0, 20) + rng.between(0, 2);
+            format!("Button A: X+0, Y+{}\nButton B: X+0, Y+{}\nPrize: X=0, Y={}", a, b, prize)
+        };
+        // with x always 0 the reference can walk the y equation instead
+        let transposed = |input: &str| {
+            let mut trials = parse_input(input).unwrap();
+            for Trial { button_a, button_b, prize } in &mut trials {
+                (button_a.x, button_a.y) = (button_a.y, button_a.x);
+                (button_b.x, button_b.y) = (button_b.y, button_b.x);
+                (prize.x, prize.y) = (prize.y, prize.x);
+            }
+            trials
+        };
+        assert_agree(
+            1000,
+            machine,
+            |input| fewest_tokens(&parse_input(input).unwrap()).unwrap(),
+            |input| reference_tokens(&transposed(input)),
+        );
+    }
+
+    #[cfg(feature = "checked-arithmetic")]
+    #[test]
+    fn overflow() {
+        use crate::error::AocError;
+
+        // each machine fits in an isize on its own, but not both together
+        let machine = "Button A: X+1, Y+0\nButton B: X+0, Y+1\nPrize: X=3000000000000000000, Y=0";
+        let trials = parse_input(&format!("{}\n\n{}", machine, machine)).unwrap();
+        assert_eq!(fewest_tokens(&trials), Err(AocError::Overflow));
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut inputs = vec![EASY.to_string()];
+        inputs.extend((0..20).map(|seed| generators::claw_machines(seed, 50)));
+        for input in inputs {
+            let trials = parse_input(&input).unwrap();
+            let right = fewest_tokens(&trials);
+            for workers in [2, 3, 8] {
+                assert_eq!(fewest_tokens_with(&trials, Parallelism::with_workers(workers)), right);
+            }
+        }
+    }
+}
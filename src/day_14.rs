@@ -1,5 +1,11 @@
 use std::os::unix::raw::off_t;
+use crate::arith::mul;
+use crate::budget;
+use crate::cycle::{floyd, Cycle};
+use crate::error::AocError;
+use crate::number_theory::{crt, lcm};
 use crate::graph::Coordinate;
+use lazy_static::lazy_static;
 use regex::Regex;
 
 
@@ -9,33 +15,74 @@ struct Velocity {
     y : isize,
 }
 
-struct Dimensions {
+pub(crate) struct Dimensions {
     width: isize,
     height: isize,
 }
 
 #[derive(Debug)]
-struct Robot {
+pub(crate) struct Robot {
     point: Coordinate,
     velocity: Velocity,
 }
 
+// The room the robots are actually in
+pub(crate) const BATHROOM: Dimensions = Dimensions {
+    width: 101,
+    height: 103,
+};
+
+// `p=x,y v=dx,dy` on every line
+pub fn parse_input(input: &str) -> Result<Vec<Robot>, AocError> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"^p=(?<x>-?\d+),(?<y>-?\d+) v=(?<x_inc>-?\d+),(?<y_inc>-?\d+)$").unwrap();
+    };
+    input
+        .lines()
+        .map(|line| {
+            let captures = RE
+                .captures(line)
+                .ok_or_else(|| AocError::parse(format!("invalid robot {:?}", line)))?;
+            Ok(Robot {
+                point: Coordinate {
+                    x: captures["x"].parse()?,
+                    y: captures["y"].parse()?,
+                },
+                velocity: Velocity {
+                    x: captures["x_inc"].parse()?,
+                    y: captures["y_inc"].parse()?,
+                },
+            })
+        })
+        .collect()
+}
+
 impl Robot {
-    fn move_for(&mut self, seconds: isize, dimensions: &Dimensions) {
-        self.point.x = (self.point.x +  self.velocity.x * seconds).rem_euclid(dimensions.width);
-        self.point.y = (self.point.y + self.velocity.y * seconds).rem_euclid(dimensions.height);
-        if self.point.x >= dimensions.width || self.point.y >= dimensions.height {
-            panic!()
-        }
+    fn move_for(&mut self, seconds: isize, dimensions: &Dimensions) -> Result<(), AocError> {
+        self.point.x = wrap(self.point.x, self.velocity.x, seconds, dimensions.width)?;
+        self.point.y = wrap(self.point.y, self.velocity.y, seconds, dimensions.height)?;
+        Ok(())
     }
 
 }
 
-fn find_quadrant_score(robots: &Vec<Robot>, dimensions: &Dimensions) -> isize {
+// Where a robot starting at `position` ends up on an axis of length `size`
+fn wrap(position: isize, velocity: isize, seconds: isize, size: isize) -> Result<isize, AocError> {
+    if size <= 0 {
+        return Err(AocError::domain(format!("the room can't be {} tiles across", size)));
+    }
+    velocity
+        .checked_mul(seconds)
+        .and_then(|distance| distance.checked_add(position))
+        .map(|position| position.rem_euclid(size))
+        .ok_or(AocError::Overflow)
+}
+
+fn find_quadrant_score(robots: &Vec<Robot>, dimensions: &Dimensions) -> Result<isize, AocError> {
     let mut quadrant_scores = [0, 0, 0, 0];
     let x_lim = dimensions.width / 2;
     let y_lim = dimensions.height / 2;
-    print_robots(robots, dimensions);
     robots
         .iter()
         .for_each(|robot| {
@@ -54,71 +101,123 @@ fn find_quadrant_score(robots: &Vec<Robot>, dimensions: &Dimensions) -> isize {
                 }
             }
         });
-    quadrant_scores.iter().product()
+    quadrant_scores.into_iter().try_fold(1, mul)
 }
 
-fn check_safety(robots: &mut Vec<Robot>, seconds: isize, dimensions: &Dimensions) -> isize {
-    robots.iter_mut().for_each(|robot| robot.move_for(seconds, dimensions));
+pub fn check_safety(
+    robots: &mut Vec<Robot>,
+    seconds: isize,
+    dimensions: &Dimensions,
+) -> Result<isize, AocError> {
+    for robot in robots.iter_mut() {
+        robot.move_for(seconds, dimensions)?;
+    }
     find_quadrant_score(robots, dimensions)
 }
-fn print_robots(robots: &Vec<Robot>, dimensions: &Dimensions) {
-
-    for row in 0..dimensions.height {
-        for column in 0..dimensions.width {
-            let coordinate=  Coordinate {x: column, y: row};
-            let ch = robots.iter().filter(|robot| robot.point == coordinate).count();
-            if ch == 0 {
-                print!(".")
-            } else {
-                print!("{}", ch)
-            }
+// Every robot wraps around independently, so the whole grid repeats with a period that
+// divides lcm(width, height). Each axis repeats on its own first, and the grid once both have,
+// with a tail of one second if robots start off the grid.
+fn find_cycle(robots: &[Robot], dimensions: &Dimensions) -> Result<Cycle, AocError> {
+    let x = robots.iter().map(|robot| (robot.point.x, robot.velocity.x));
+    let x = axis_cycle(x, dimensions.width);
+    let y = robots.iter().map(|robot| (robot.point.y, robot.velocity.y));
+    let y = axis_cycle(y, dimensions.height);
+    let length = isize::try_from(x.length)
+        .ok()
+        .zip(isize::try_from(y.length).ok())
+        .and_then(|(x, y)| lcm(x, y))
+        .and_then(|length| usize::try_from(length).ok())
+        .ok_or(AocError::Overflow)?;
+    Ok(Cycle {
+        tail: x.tail.max(y.tail),
+        length,
+    })
+}
 
+fn axis_cycle(robots: impl Iterator<Item = (isize, isize)>, size: isize) -> Cycle {
+    let (start, velocities): (Vec<_>, Vec<_>) = robots.unzip();
+    let step = |positions: &Vec<isize>| {
+        positions
+            .iter()
+            .zip(&velocities)
+            .map(|(&position, &velocity)| axis_position(position, velocity, 1, size))
+            .collect()
+    };
+    floyd(&start, step)
+}
+
+// The picture shows up when the robots bunch together. Their x positions repeat every `width`
+// seconds and their y positions every `height` seconds, so find the tightest second for each
+// axis on its own and combine the two with the chinese remainder theorem.
+pub fn find_alignment_time(robots: &[Robot], dimensions: &Dimensions) -> Result<isize, AocError> {
+    let x_time = tightest(dimensions.width, |seconds| {
+        spread(robots.iter().map(|robot| {
+            axis_position(robot.point.x, robot.velocity.x, seconds, dimensions.width)
+        }))
+    })?
+    .ok_or_else(|| AocError::domain("the room has no columns"))?;
+    let y_time = tightest(dimensions.height, |seconds| {
+        spread(robots.iter().map(|robot| {
+            axis_position(robot.point.y, robot.velocity.y, seconds, dimensions.height)
+        }))
+    })?
+    .ok_or_else(|| AocError::domain("the room has no rows"))?;
+    let (seconds, _) = crt(&[(x_time, dimensions.width), (y_time, dimensions.height)])
+        .ok_or_else(|| AocError::domain("the robots never line up on both axes at once"))?;
+    // the robots can repeat sooner than width * height, and the picture first shows up within
+    // one lap of the positions they actually repeat
+    let seconds = usize::try_from(seconds).map_err(|_| AocError::Overflow)?;
+    let seconds = find_cycle(robots, dimensions)?.reduce(seconds);
+    isize::try_from(seconds).map_err(|_| AocError::Overflow)
+}
+
+// The first second below `size` with the smallest spread, None if there are no seconds
+fn tightest(size: isize, spread_at: impl Fn(isize) -> isize) -> Result<Option<isize>, AocError> {
+    let mut tightest = None;
+    for seconds in 0..size {
+        budget::step()?;
+        let spread = spread_at(seconds);
+        if tightest.is_none_or(|(_, least)| spread < least) {
+            tightest = Some((seconds, spread));
         }
-        println!()
     }
+    Ok(tightest.map(|(seconds, _)| seconds))
+}
 
-    println!()
+// Like `wrap`, but reduced first so it can't overflow for any start or velocity as long as
+// `seconds` is less than `size`
+fn axis_position(position: isize, velocity: isize, seconds: isize, size: isize) -> isize {
+    (position.rem_euclid(size) + velocity.rem_euclid(size) * seconds).rem_euclid(size)
 }
+
+// n times the sum of squared deviations from the mean, which keeps the variance in integers
+fn spread(values: impl Iterator<Item = isize>) -> isize {
+    let (n, sum, sum_of_squares) = values.fold((0, 0, 0), |(n, sum, squares), value| {
+        (n + 1, sum + value, squares + value * value)
+    });
+    n * sum_of_squares - sum * sum
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::day_14::{check_safety, Dimensions, Robot, Velocity};
+    use crate::day_14::{
+        check_safety, find_alignment_time, find_cycle, parse_input, Dimensions, Robot, Velocity,
+    };
     use crate::graph::Coordinate;
-    use lazy_static::lazy_static;
-    use regex::Regex;
     use crate::answers::DAY_14_EASY;
 
     const PREAMBLE: &str = include_str!("../resources/day_14/preamble.txt");
     const INPUT: &str = include_str!("../resources/day_14/input.txt");
 
-    fn parse_input(input: &str) -> Vec<Robot> {
-        lazy_static! {
-            static ref RE: Regex =
-                Regex::new(r"^p=(?<x>-?\d+),(?<y>-?\d+) v=(?<x_inc>-?\d+),(?<y_inc>-?\d+)$").unwrap();
-        };
-        input
-            .lines()
-            .map(|line| {
-                let captures = RE.captures(line).unwrap();
-                let x = captures.name("x").unwrap().as_str().parse().unwrap();
-                let y = captures.name("y").unwrap().as_str().parse().unwrap();
-                let x_inc = captures.name("x_inc").unwrap().as_str().parse().unwrap();
-                let y_inc = captures.name("y_inc").unwrap().as_str().parse().unwrap();
-                Robot {
-                    point: Coordinate { x, y },
-                    velocity: Velocity{x: x_inc, y: y_inc},
-                }
-            })
-            .collect()
-    }
     #[test]
     fn preamble() {
         let dimensions: Dimensions = Dimensions {
             width: 11,
             height: 7,
         };
-        let mut input = parse_input(PREAMBLE);
+        let mut input = parse_input(PREAMBLE).unwrap();
         println!("{:?}", input.len());
-        let left = check_safety(&mut input, 100, &dimensions);
+        let left = check_safety(&mut input, 100, &dimensions).unwrap();
         let right = 12;
         assert_eq!(left, right)
     }
@@ -129,10 +228,53 @@ mod tests {
             width: 101,
             height: 103,
         };
-        let mut input = parse_input(INPUT);
-        let left = check_safety(&mut input, 100, &dimensions);
+        let mut input = parse_input(INPUT).unwrap();
+        let left = check_safety(&mut input, 100, &dimensions).unwrap();
         let right = DAY_14_EASY;
         assert_eq!(left, right)
         // let right = 0;
     }
+
+    #[test]
+    fn period() {
+        let dimensions: Dimensions = Dimensions {
+            width: 11,
+            height: 7,
+        };
+        let input = parse_input(PREAMBLE).unwrap();
+        let left = find_cycle(&input, &dimensions).unwrap().length;
+        assert_eq!(77 % left, 0);
+
+        let dimensions: Dimensions = Dimensions {
+            width: 101,
+            height: 103,
+        };
+        let input = parse_input(INPUT).unwrap();
+        let left = find_cycle(&input, &dimensions).unwrap().length;
+        let right = 101 * 103;
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn alignment() {
+        let dimensions: Dimensions = Dimensions {
+            width: 101,
+            height: 103,
+        };
+        let target = Coordinate { x: 40, y: 60 };
+        let seconds = 7777;
+        let robots = (1..20)
+            .map(|i| {
+                let velocity = Velocity { x: i, y: -2 * i };
+                let point = Coordinate {
+                    x: (target.x - velocity.x * seconds).rem_euclid(dimensions.width),
+                    y: (target.y - velocity.y * seconds).rem_euclid(dimensions.height),
+                };
+                Robot { point, velocity }
+            })
+            .collect::<Vec<_>>();
+        let left = find_alignment_time(&robots, &dimensions);
+        let right = Ok(seconds);
+        assert_eq!(left, right);
+    }
 }
@@ -5,76 +5,106 @@ Entities:
   - robot
   - box
 */
+use crate::arith::{add, mul};
+use crate::budget;
+use crate::error::AocError;
 use crate::graph::{Coordinate, Direction, Index, Matrix};
+use crate::tile::{parse_grid, rectangular, render_grid, tile};
 use std::borrow::Cow;
 use std::collections::{HashSet, VecDeque};
 use std::mem;
 
-type Warehouse = Vec<Vec<char>>;
+type Warehouse = Vec<Vec<WarehouseTile>>;
 type Instructions = Vec<Direction>;
 
-const BOX: char = 'O';
-const EMPTY: char = '.';
-const ROBOT: char = '@';
-const WALL: char = '#';
+tile! {
+    pub(crate) enum WarehouseTile {
+        Box => 'O',
+        Empty => '.',
+        Robot => '@',
+        Wall => '#',
+        BoxLeft => '[',
+        BoxRight => ']',
+    }
+}
 
-fn parse_instruction(instruction: char) -> Direction {
+fn parse_instruction(instruction: char) -> Result<Direction, AocError> {
     match instruction {
-        '^' => Direction::North,
-        '>' => Direction::East,
-        '<' => Direction::West,
-        'v' => Direction::South,
-        ch @ _ => panic!("Invalid instruction {}", ch),
+        '^' => Ok(Direction::North),
+        '>' => Ok(Direction::East),
+        '<' => Ok(Direction::West),
+        'v' => Ok(Direction::South),
+        ch @ _ => Err(AocError::parse(format!("invalid instruction {:?}", ch))),
     }
 }
 
-fn parse_input(input: &str) -> (Warehouse, Instructions) {
-    let (warehouse, instructions) = input.split_once("\n\n").unwrap();
+pub fn parse_input(input: &str) -> Result<(Warehouse, Instructions), AocError> {
+    let (warehouse, instructions) = input
+        .split_once("\n\n")
+        .ok_or_else(|| AocError::parse("no blank line between the warehouse and the instructions"))?;
 
-    let warehouse = warehouse.lines().map(|l| l.chars().collect()).collect();
+    let warehouse = rectangular(parse_grid(warehouse)?)?;
 
     let instructions = instructions
         .chars()
-        .filter_map(|ch| {
-            if ch == '\n' {
-                None
-            } else {
-                Some(parse_instruction(ch))
-            }
-        })
-        .collect();
-    (warehouse, instructions)
+        .filter(|ch| *ch != '\n')
+        .map(parse_instruction)
+        .collect::<Result<_, _>>()?;
+    Ok((warehouse, instructions))
 }
 
-fn update_warehouse(warehouse: &mut Warehouse, instructions: &Instructions) {
-    let Some(mut robot_location) = find_robot(warehouse) else {
-        return;
-    };
-    instructions.iter().for_each(|instruction| {
-        if let Cow::Owned(new_location) = try_move(warehouse, &robot_location, &instruction) {
+pub fn update_warehouse(warehouse: &mut Warehouse, instructions: &Instructions) -> Result<(), AocError> {
+    let mut robot_location = find_robot(warehouse)?;
+    for instruction in instructions {
+        budget::step()?;
+        if let Cow::Owned(new_location) = try_move(warehouse, &robot_location, &instruction)? {
             robot_location = new_location;
         }
-    })
+    }
+    Ok(())
 }
-fn update_wide_warehouse(warehouse: &mut Warehouse, instructions: &Instructions) {
-    let Some(mut robot_location) = find_robot(warehouse) else {
-        return;
-    };
-    instructions.iter().for_each(|instruction| {
+pub fn update_wide_warehouse(
+    warehouse: &mut Warehouse,
+    instructions: &Instructions,
+) -> Result<(), AocError> {
+    let mut robot_location = find_robot(warehouse)?;
+    for instruction in instructions {
+        budget::step()?;
         // println!("moving {:?}", &instruction);
-        if let Cow::Owned(new_location) = try_wide_move(warehouse, &robot_location, &instruction) {
+        if let Cow::Owned(new_location) = try_wide_move(warehouse, &robot_location, &instruction)? {
             robot_location = new_location;
         }
         // print_warehouse(&warehouse);
         // println!();
-    })
+    }
+    Ok(())
+}
+
+fn get_tile(warehouse: &Warehouse, location: &Coordinate) -> Result<WarehouseTile, AocError> {
+    location
+        .try_into()
+        .ok()
+        .and_then(|index: Index| warehouse.get_scalar(&index).copied())
+        .ok_or_else(|| AocError::domain("the robot walked out of the warehouse"))
+}
+
+fn set_tile(warehouse: &mut Warehouse, location: &Coordinate, tile: WarehouseTile) -> Result<(), AocError> {
+    let index: Index = location
+        .try_into()
+        .map_err(|_| AocError::domain("the robot walked out of the warehouse"))?;
+    warehouse.set_scalar(&index, tile);
+    Ok(())
+}
+
+fn shift(location: &Coordinate, direction: &Direction) -> Result<Coordinate, AocError> {
+    location.shift(direction).ok_or(AocError::Overflow)
 }
 
 fn try_wide_move<'a>(
     warehouse: &mut Warehouse,
     robot_location: &'a Coordinate,
     direction: &Direction,
-) -> Cow<'a, Coordinate> {
+) -> Result<Cow<'a, Coordinate>, AocError> {
     // first step, check whether there's a box ahead of the robot
     // north south is different from west east
 
@@ -87,23 +117,19 @@ fn try_wide_move<'a>(
             let mut to_shift = Vec::new();
 
             while let Some(last_point) = bfs_queue.pop_front() {
-                let ahead_point = last_point.shift(direction).unwrap();
-                let ahead_tile = warehouse
-                    .get_scalar(&ahead_point.try_into().unwrap())
-                    .unwrap();
+                let ahead_point = shift(&last_point, direction)?;
+                let ahead_tile = get_tile(warehouse, &ahead_point)?;
 
                 match ahead_tile {
-                    '#' => return Cow::Borrowed(robot_location),
-                    '[' => {
-                        let adjacent_point = ahead_point
-                            .shift(&Direction::East).unwrap();
+                    WarehouseTile::Wall => return Ok(Cow::Borrowed(robot_location)),
+                    WarehouseTile::BoxLeft => {
+                        let adjacent_point = shift(&ahead_point, &Direction::East)?;
                         bfs_queue.push_back(ahead_point);
                         bfs_queue.push_back(adjacent_point);
                         to_shift.push(last_point);
                     }
-                    ']' => {
-                        let adjacent_point = ahead_point
-                            .shift(&Direction::West).unwrap();
+                    WarehouseTile::BoxRight => {
+                        let adjacent_point = shift(&ahead_point, &Direction::West)?;
 
                         if bfs_queue.back() != Some(&ahead_point) {
                             bfs_queue.push_back(adjacent_point);
@@ -111,97 +137,96 @@ fn try_wide_move<'a>(
                         }
                         to_shift.push(last_point);
                     }
-                    '.' => {
+                    WarehouseTile::Empty => {
                         to_shift.push(last_point);
                         continue
                     },
-                    ch@_ => panic!("Invalid tile: {}", ch),
+                    tile @ (WarehouseTile::Box | WarehouseTile::Robot) => {
+                        return Err(AocError::unreachable(format!("{} in a wide warehouse", tile)))
+                    }
                 }
             }
-            println!();
 
             // now we have the walls, we just need to update them all (FUCK).
-            to_shift.into_iter().rev().for_each(|p| {
-                let last_tile =warehouse.get_scalar(&p.try_into().unwrap()).unwrap();
-                let new_position = p.shift(&direction).unwrap();
-                warehouse.set_scalar(&new_position.try_into().unwrap(), *last_tile);
-                warehouse.set_scalar(&p.try_into().unwrap(), '.');
+            for p in to_shift.into_iter().rev() {
+                let last_tile = get_tile(warehouse, &p)?;
+                let new_position = shift(&p, &direction)?;
+                set_tile(warehouse, &new_position, last_tile)?;
+                set_tile(warehouse, &p, WarehouseTile::Empty)?;
                 // print_warehouse(&warehouse);
 
-            });
-            warehouse.set_scalar(&robot_location.try_into().unwrap(), '.');
-            Cow::Owned(robot_location.shift(&direction).unwrap())
+            }
+            set_tile(warehouse, robot_location, WarehouseTile::Empty)?;
+            Ok(Cow::Owned(shift(robot_location, &direction)?))
         }
         Direction::West | Direction::East => {
             let mut walls_between = Vec::new();
-            let mut next_coordinate = robot_location.shift(&direction).unwrap();
-            let mut next_tile = warehouse
-                .get_scalar(&next_coordinate.try_into().unwrap())
-                .unwrap();
+            let mut next_coordinate = shift(robot_location, &direction)?;
+            let mut next_tile = get_tile(warehouse, &next_coordinate)?;
             loop {
                 match next_tile {
-                    '#' => {
-                        return Cow::Borrowed(robot_location);
+                    WarehouseTile::Wall => {
+                        return Ok(Cow::Borrowed(robot_location));
                     }
-                    '.' => {
+                    WarehouseTile::Empty => {
                         let direction = direction.get_opposite();
                         while let Some(next_tile) = walls_between.pop() {
-                            warehouse.set_scalar(&next_coordinate.try_into().unwrap(), next_tile);
-                            next_coordinate = next_coordinate.shift(&direction).unwrap()
+                            set_tile(warehouse, &next_coordinate, next_tile)?;
+                            next_coordinate = shift(&next_coordinate, &direction)?
                         }
-                        warehouse.set_scalar(&next_coordinate.try_into().unwrap(), ROBOT);
-                        warehouse.set_scalar(&robot_location.try_into().unwrap(), EMPTY);
-                        return Cow::Owned(next_coordinate);
+                        set_tile(warehouse, &next_coordinate, WarehouseTile::Robot)?;
+                        set_tile(warehouse, robot_location, WarehouseTile::Empty)?;
+                        return Ok(Cow::Owned(next_coordinate));
                     }
-                    '[' | ']' => {
-                        walls_between.push(*next_tile);
-                        next_coordinate = next_coordinate.shift(&direction).unwrap();
-                        next_tile = warehouse
-                            .get_scalar(&next_coordinate.try_into().unwrap())
-                            .unwrap();
+                    WarehouseTile::BoxLeft | WarehouseTile::BoxRight => {
+                        walls_between.push(next_tile);
+                        next_coordinate = shift(&next_coordinate, &direction)?;
+                        next_tile = get_tile(warehouse, &next_coordinate)?;
+                    }
+                    WarehouseTile::Box | WarehouseTile::Robot => {
+                        return Err(AocError::unreachable(format!("{} in a wide warehouse", next_tile)))
                     }
-                    _ => panic!("Invalid tile {}", next_tile),
                 }
             }
         }
-        _ => panic!(),
+        _ => Err(AocError::unreachable(format!("robot moving {:?}", direction))),
     }
 }
 
-fn find_robot(warehouse: &Warehouse) -> Option<Coordinate> {
-    for row in 0..warehouse.len() {
-        for column in 0..warehouse[0].len() {
-            if warehouse[row][column] == ROBOT {
-                return Some(Coordinate {
+fn find_robot(warehouse: &Warehouse) -> Result<Coordinate, AocError> {
+    for (row, tiles) in warehouse.iter().enumerate() {
+        for (column, tile) in tiles.iter().enumerate() {
+            if *tile == WarehouseTile::Robot {
+                return Ok(Coordinate {
                     x: column as isize,
                     y: row as isize,
                 });
             }
         }
     }
-    None
+    Err(AocError::domain("no robot found in the warehouse"))
 }
 
 fn try_move<'a>(
     warehouse: &mut Warehouse,
     robot_location: &'a Coordinate,
     direction: &Direction,
-) -> Cow<'a, Coordinate> {
-    let next_space = get_next_empty_tile(robot_location, warehouse, &direction);
+) -> Result<Cow<'a, Coordinate>, AocError> {
+    let next_space = get_next_empty_tile(robot_location, warehouse, &direction)?;
     match next_space {
-        None => Cow::Borrowed(robot_location),
+        None => Ok(Cow::Borrowed(robot_location)),
         Some(next) => {
-            let adjacent = robot_location.shift(direction).unwrap();
+            let adjacent = shift(robot_location, direction)?;
 
-            warehouse.set_scalar(&robot_location.try_into().unwrap(), EMPTY);
+            set_tile(warehouse, robot_location, WarehouseTile::Empty)?;
 
             if adjacent == next {
-                warehouse.set_scalar(&next.try_into().unwrap(), ROBOT);
+                set_tile(warehouse, &next, WarehouseTile::Robot)?;
             } else {
-                warehouse.set_scalar(&next.try_into().unwrap(), BOX);
-                warehouse.set_scalar(&adjacent.try_into().unwrap(), ROBOT);
+                set_tile(warehouse, &next, WarehouseTile::Box)?;
+                set_tile(warehouse, &adjacent, WarehouseTile::Robot)?;
             }
-            Cow::Owned(adjacent)
+            Ok(Cow::Owned(adjacent))
         }
     }
 }
@@ -210,85 +235,78 @@ fn get_next_empty_tile(
     robot_location: &Coordinate,
     warehouse: &Warehouse,
     direction: &Direction,
-) -> Option<Coordinate> {
-    let mut next_location = robot_location.shift(direction).unwrap();
+) -> Result<Option<Coordinate>, AocError> {
+    let mut next_location = shift(robot_location, direction)?;
     loop {
-        let next_tile = warehouse
-            .get_scalar(&next_location.try_into().unwrap())
-            .unwrap();
+        let next_tile = get_tile(warehouse, &next_location)?;
         match next_tile {
-            &WALL => return None,
-            &BOX => {
-                next_location = next_location.shift(direction).unwrap();
+            WarehouseTile::Wall => return Ok(None),
+            WarehouseTile::Box => {
+                next_location = shift(&next_location, direction)?;
+            }
+            WarehouseTile::Empty => {
+                return Ok(Some(next_location));
             }
-            &EMPTY => {
-                return Some(next_location);
+            tile => {
+                return Err(AocError::unreachable(format!("{} in a narrow warehouse", tile)))
             }
-            _ => unreachable!(),
         }
     }
 }
-fn count_score(warehouse: &Warehouse) -> usize {
+pub fn count_score(warehouse: &Warehouse) -> Result<usize, AocError> {
     let mut score = 0;
     for row in 0..warehouse.len() {
         for column in 0..warehouse[0].len() {
-            let tile = warehouse.get_scalar(&Index { x: column, y: row }).unwrap();
-            if tile == &BOX {
-                let gps_coordinate = 100 * row + column;
-                score += gps_coordinate
+            let tile = warehouse
+                .get_scalar(&Index { x: column, y: row })
+                .ok_or_else(|| AocError::unreachable("the warehouse isn't rectangular"))?;
+            if tile == &WarehouseTile::Box {
+                let gps_coordinate = add(mul(100, row)?, column)?;
+                score = add(score, gps_coordinate)?;
             }
         }
     }
-    score
+    Ok(score)
 }
-fn count_wide_score(warehouse: &Warehouse) -> usize {
+pub fn count_wide_score(warehouse: &Warehouse) -> Result<usize, AocError> {
     let mut score = 0;
     for row in 0..warehouse.len() {
         for column in 0..warehouse[0].len() {
-            let tile = warehouse.get_scalar(&Index { x: column, y: row }).unwrap();
-            if tile == &'[' {
-                let gps_coordinate = 100 * row + column;
-                score += gps_coordinate
+            let tile = warehouse
+                .get_scalar(&Index { x: column, y: row })
+                .ok_or_else(|| AocError::unreachable("the warehouse isn't rectangular"))?;
+            if tile == &WarehouseTile::BoxLeft {
+                let gps_coordinate = add(mul(100, row)?, column)?;
+                score = add(score, gps_coordinate)?;
             }
         }
     }
-    score
+    Ok(score)
 
 }
 fn print_warehouse(warehouse: &Warehouse) {
-    for row in 0..warehouse.len() {
-        for column in 0..warehouse[0].len() {
-            print!(
-                "{}",
-                warehouse.get_scalar(&Index { x: column, y: row }).unwrap()
-            );
-        }
-        println!();
-    }
+    println!("{}", render_grid(warehouse));
 }
 
-fn resize_warehouse(warehouse: &mut Warehouse) {
-    let row_n = warehouse.len();
-    for row in 0..row_n {
-        let new_row = Vec::with_capacity(row_n * 2);
-
-        let old_row = mem::replace(&mut warehouse[row], new_row);
-        let mut new_row = warehouse.get_mut(row).unwrap();
+pub fn resize_warehouse(warehouse: &mut Warehouse) {
+    for new_row in warehouse.iter_mut() {
+        let old_row = mem::take(new_row);
+        new_row.reserve(old_row.len() * 2);
 
-        old_row.into_iter().for_each(|ch| {
-            if ch == 'O' {
+        old_row.into_iter().for_each(|tile| {
+            if tile == WarehouseTile::Box {
 
-                new_row.push('[');
-                new_row.push(']');
-            } else if ch == '@' {
+                new_row.push(WarehouseTile::BoxLeft);
+                new_row.push(WarehouseTile::BoxRight);
+            } else if tile == WarehouseTile::Robot {
 
-                new_row.push('@');
-                new_row.push('.');
+                new_row.push(WarehouseTile::Robot);
+                new_row.push(WarehouseTile::Empty);
             }
             else {
 
-                new_row.push(ch);
-                new_row.push(ch);
+                new_row.push(tile);
+                new_row.push(tile);
             }
         });
     }
@@ -299,6 +317,7 @@ fn resize_warehouse(warehouse: &mut Warehouse) {
 mod tests {
     use crate::answers::{DAY_15_EASY, DAY_15_HARD};
     use crate::day_15::{count_score, count_wide_score, parse_input, print_warehouse, resize_warehouse, update_warehouse, update_wide_warehouse, Warehouse};
+    use crate::error::AocError;
     use crate::graph::{Index, Matrix};
 
     const PREAMBLE: &str = include_str!("../resources/day_15/preamble.txt");
@@ -306,49 +325,61 @@ mod tests {
 
     #[test]
     fn preamble() {
-        let (mut warehouse, instructions) = parse_input(PREAMBLE);
+        let (mut warehouse, instructions) = parse_input(PREAMBLE).unwrap();
         print_warehouse(&warehouse);
         println!();
-        update_warehouse(&mut warehouse, &instructions);
+        update_warehouse(&mut warehouse, &instructions).unwrap();
         print_warehouse(&warehouse);
         println!();
-        let left = count_score(&warehouse);
+        let left = count_score(&warehouse).unwrap();
         let right = 10092;
         assert_eq!(left, right);
     }
     #[test]
     fn easy() {
-        let (mut warehouse, instructions) = parse_input(EASY);
-        update_warehouse(&mut warehouse, &instructions);
-        let left = count_score(&warehouse);
+        let (mut warehouse, instructions) = parse_input(EASY).unwrap();
+        update_warehouse(&mut warehouse, &instructions).unwrap();
+        let left = count_score(&warehouse).unwrap();
         let right = DAY_15_EASY;
         assert_eq!(left, right);
     }
 
     #[test]
     fn hard_preamble() {
-        let (mut warehouse, instructions) = parse_input(PREAMBLE);
+        let (mut warehouse, instructions) = parse_input(PREAMBLE).unwrap();
         resize_warehouse(&mut warehouse);
         println!("###START###");
         print_warehouse(&warehouse);
-        update_wide_warehouse(&mut warehouse, &instructions);
+        update_wide_warehouse(&mut warehouse, &instructions).unwrap();
         println!("###RESULT###");
         print_warehouse(&warehouse);
-        let left = count_wide_score(&warehouse);
+        let left = count_wide_score(&warehouse).unwrap();
         let right = 9021;
         assert_eq!(left, right);
     }
     #[test]
     fn hard() {
-        let (mut warehouse, instructions) = parse_input(EASY);
+        let (mut warehouse, instructions) = parse_input(EASY).unwrap();
         resize_warehouse(&mut warehouse);
         println!("###START###");
         print_warehouse(&warehouse);
-        update_wide_warehouse(&mut warehouse, &instructions);
+        update_wide_warehouse(&mut warehouse, &instructions).unwrap();
         println!("###RESULT###");
         print_warehouse(&warehouse);
-        let left = count_wide_score(&warehouse);
+        let left = count_wide_score(&warehouse).unwrap();
         let right = DAY_15_HARD;
         assert_eq!(left, right);
     }
+
+    #[test]
+    fn invalid_input() {
+        let left = parse_input("#@.#\n\n<x>");
+        let right = Err(AocError::parse("invalid instruction 'x'"));
+        assert_eq!(left.map(|_| ()), right);
+
+        let (mut warehouse, instructions) = parse_input("#..#\n\n<>").unwrap();
+        let left = update_warehouse(&mut warehouse, &instructions);
+        let right = Err(AocError::domain("no robot found in the warehouse"));
+        assert_eq!(left, right);
+    }
 }
@@ -1,4 +1,9 @@
+use crate::arith::add;
+use crate::bitset::ScoreGrid;
+use crate::budget;
+use crate::error::AocError;
 use crate::graph::{Coordinate, Direction, Matrix};
+use crate::tile::{parse_grid, rectangular, tile};
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 use std::os::raw::c_float;
@@ -6,12 +11,27 @@ use std::os::raw::c_float;
 // Reindeer maze
 // BFS -> keep searching the maze until a
 
+type Maze = Vec<Vec<MazeTile>>;
+
+tile! {
+    pub(crate) enum MazeTile {
+        Start => 'S',
+        End => 'E',
+        Floor => '.',
+        Wall => '#',
+    }
+}
+
+pub fn parse_input(input: &str) -> Result<Maze, AocError> {
+    rectangular(parse_grid(input)?)
+}
+
 struct Reindeer {
     location: Coordinate,
     direction: Direction,
 }
 
-fn get_shortest_path_score(maze: &Vec<Vec<char>>) -> Option<usize> {
+pub fn get_shortest_path_score(maze: &Maze) -> Result<usize, AocError> {
     let start = find_start(maze)?;
     let reindeer = Reindeer {
         location: start,
@@ -21,14 +41,14 @@ fn get_shortest_path_score(maze: &Vec<Vec<char>>) -> Option<usize> {
     let mut queue = VecDeque::new();
     queue.push_front((0usize, reindeer));
 
-    let mut visited = HashMap::new();
+    let mut visited = ScoreGrid::for_grid(maze);
 
     while let Some((score, Reindeer {
         location,
         direction,
     })) = queue.pop_back()
     {
-
+        budget::step()?;
 
         match visited.get(&location) {
             Some(best_score) if *best_score <= score => {
@@ -36,38 +56,48 @@ fn get_shortest_path_score(maze: &Vec<Vec<char>>) -> Option<usize> {
             },
 
             _ => {
-               visited.insert(location, score);
+               visited
+                   .insert(&location, score)
+                   .ok_or_else(|| AocError::unreachable("the reindeer left the maze"))?;
             }
         }
 
         // try current direction.
-        let next = location.shift(&direction).unwrap();
-        let next_tile = maze.get_scalar(&next.try_into().unwrap()).unwrap();
+        let next = location.shift(&direction).ok_or(AocError::Overflow)?;
+        let next_tile = get_tile(maze, &next);
 
-        if next_tile == &'E' {
+        if next_tile == Some(MazeTile::End) {
 
-           finishers.push((score + 1));
-        } else if next_tile == &'.' {
-            queue.push_front((score + 1 , Reindeer {location: next, direction}));
+           finishers.push(add(score, 1)?);
+        } else if next_tile == Some(MazeTile::Floor) {
+            queue.push_front((add(score, 1)?, Reindeer {location: next, direction}));
         }
 
         // try the other two directions
 
         let directions = [direction.rotate_90_degrees_clockwise(), direction.rotate_90_degrees_counter_clockwise()];
-        directions.into_iter().for_each(|direction| {
+        for direction in directions {
             // try current direction.
-            let next = location.shift(&direction).unwrap();
-            let next_tile = maze.get_scalar(&next.try_into().unwrap()).unwrap();
+            let next = location.shift(&direction).ok_or(AocError::Overflow)?;
+            let next_tile = get_tile(maze, &next);
 
-            if next_tile == &'E' {
-                finishers.push((score + 1001));
-            } else if next_tile == &'.' {
-                queue.push_front((score + 1001 , Reindeer {location: next, direction}));
+            if next_tile == Some(MazeTile::End) {
+                finishers.push(add(score, 1001)?);
+            } else if next_tile == Some(MazeTile::Floor) {
+                queue.push_front((add(score, 1001)?, Reindeer {location: next, direction}));
             }
 
-        })
+        }
     };
-    finishers.into_iter().min()
+    finishers
+        .into_iter()
+        .min()
+        .ok_or_else(|| AocError::domain("the end can't be reached from the start"))
+}
+
+// Off the edge of the maze counts as no tile at all, which blocks the reindeer like a wall
+fn get_tile(maze: &Maze, location: &Coordinate) -> Option<MazeTile> {
+    maze.get_scalar(&location.try_into().ok()?).copied()
 }
 
 
@@ -134,44 +164,39 @@ fn get_shortest_path_score(maze: &Vec<Vec<char>>) -> Option<usize> {
 
 
 // }
-fn find_start(maze: &Vec<Vec<char>>) -> Option<Coordinate> {
-    for row in 0..maze.len() {
-        for column in 0..maze[0].len() {
-            let tile = maze[row][column];
-            if tile == 'S' {
-                return Some(Coordinate {
+fn find_start(maze: &Maze) -> Result<Coordinate, AocError> {
+    for (row, tiles) in maze.iter().enumerate() {
+        for (column, tile) in tiles.iter().enumerate() {
+            if *tile == MazeTile::Start {
+                return Ok(Coordinate {
                     x: column as isize,
                     y: row as isize,
                 });
             }
         }
     }
-    None
+    Err(AocError::domain("no start found in the maze"))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::answers::DAY_16_EASY;
-    use crate::day_16::get_shortest_path_score;
+    use crate::day_16::{get_shortest_path_score, parse_input};
     const PREAMBLE: &str = include_str!("../resources/day_16/preamble.txt");
     const EASY: &str = include_str!("../resources/day_16/easy.txt");
 
-    fn parse_input(input: &str) -> Vec<Vec<char>> {
-        input.lines().map(|l| l.chars().collect()).collect()
-    }
-
     #[test]
     fn preamble() {
-        let input = parse_input(PREAMBLE);
+        let input = parse_input(PREAMBLE).unwrap();
         let left = get_shortest_path_score(&input);
-        let right = Some(7036);
+        let right = Ok(7036);
         assert_eq!(left, right);
     }
     #[test]
     fn easy() {
-        let input = parse_input(EASY);
+        let input = parse_input(EASY).unwrap();
         let left = get_shortest_path_score(&input);
-        let right = Some(DAY_16_EASY);
+        let right = Ok(DAY_16_EASY);
         assert_eq!(left, right)
     }
 }
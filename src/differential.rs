@@ -0,0 +1,25 @@
+// Differential testing: run a solver and a slow reference on lots of generated inputs and
+// check they agree.
+use std::fmt::Debug;
+
+// Compares `fast` and `reference` on the inputs generated for seeds 0..cases, failing with the
+// seed and the input of the first disagreement.
+pub fn assert_agree<T, G, F, R>(cases: u64, generate: G, fast: F, reference: R)
+where
+    T: Debug + PartialEq,
+    G: Fn(u64) -> String,
+    F: Fn(&str) -> T,
+    R: Fn(&str) -> T,
+{
+    for seed in 0..cases {
+        let input = generate(seed);
+        let left = fast(&input);
+        let right = reference(&input);
+        assert_eq!(left, right, "seed {} disagrees on\n{}", seed, input);
+    }
+}
+
+// Small inputs give the most readable failures, so sizes cycle through 1..=max
+pub fn small_size(seed: u64, max: usize) -> usize {
+    seed as usize % max + 1
+}
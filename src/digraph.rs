@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+
+#[derive(Clone, Debug)]
+pub struct DiGraph<N> {
+    nodes: Vec<N>,
+    lookup: HashMap<N, usize>,
+    successors: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleError<N> {
+    pub cycle: Vec<N>,
+}
+
+impl<N: Debug> Display for CycleError<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "graph contains a cycle: ")?;
+        for node in &self.cycle {
+            write!(f, "{:?} -> ", node)?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{:?}", first),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<N: Debug> Error for CycleError<N> {}
+
+impl<N: Clone + Eq + Hash> Default for DiGraph<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Clone + Eq + Hash> DiGraph<N> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            lookup: HashMap::new(),
+            successors: Vec::new(),
+        }
+    }
+
+    pub fn from_edges<'a, I>(edges: I) -> Self
+    where
+        I: IntoIterator<Item = &'a (N, N)>,
+        N: 'a,
+    {
+        let mut graph = Self::new();
+        edges
+            .into_iter()
+            .for_each(|(from, to)| graph.add_edge(from.clone(), to.clone()));
+        graph
+    }
+
+    pub fn add_node(&mut self, node: N) -> usize {
+        if let Some(id) = self.lookup.get(&node) {
+            return *id;
+        }
+        let id = self.nodes.len();
+        self.lookup.insert(node.clone(), id);
+        self.nodes.push(node);
+        self.successors.push(Vec::new());
+        id
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        if !self.successors[from].contains(&to) {
+            self.successors[from].push(to);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    pub fn contains_node(&self, node: &N) -> bool {
+        self.lookup.contains_key(node)
+    }
+
+    pub fn contains_edge(&self, from: &N, to: &N) -> bool {
+        match (self.lookup.get(from), self.lookup.get(to)) {
+            (Some(from), Some(to)) => self.successors[*from].contains(to),
+            _ => false,
+        }
+    }
+
+    pub fn successors(&self, node: &N) -> impl Iterator<Item = &N> + '_ {
+        self.lookup
+            .get(node)
+            .into_iter()
+            .flat_map(|id| self.successors[*id].iter().map(|s| &self.nodes[*s]))
+    }
+
+    // Kahn's algorithm. Nodes with no ordering between them keep their insertion order.
+    pub fn topological_sort(&self) -> Result<Vec<N>, CycleError<N>> {
+        let mut in_degree = vec![0; self.len()];
+        self.successors
+            .iter()
+            .flatten()
+            .for_each(|to| in_degree[*to] += 1);
+
+        let mut queue = (0..self.len())
+            .filter(|id| in_degree[*id] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(id) = queue.pop_front() {
+            order.push(self.nodes[id].clone());
+            for to in &self.successors[id] {
+                in_degree[*to] -= 1;
+                if in_degree[*to] == 0 {
+                    queue.push_back(*to);
+                }
+            }
+        }
+
+        if order.len() == self.len() {
+            Ok(order)
+        } else {
+            Err(CycleError {
+                cycle: self.find_cycle().unwrap(),
+            })
+        }
+    }
+
+    // Tarjan's algorithm, unrolled so deep graphs don't blow the stack. Components come out
+    // in reverse topological order.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<N>> {
+        self.component_ids()
+            .into_iter()
+            .map(|component| component.into_iter().map(|id| self.nodes[id].clone()).collect())
+            .collect()
+    }
+
+    fn component_ids(&self) -> Vec<Vec<usize>> {
+        let n = self.len();
+        let mut next_index = 0;
+        let mut indices: Vec<Option<usize>> = vec![None; n];
+        let mut low_links = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+
+        for start in 0..n {
+            if indices[start].is_some() {
+                continue;
+            }
+            let mut call_stack = vec![(start, 0)];
+            indices[start] = Some(next_index);
+            low_links[start] = next_index;
+            next_index += 1;
+            stack.push(start);
+            on_stack[start] = true;
+
+            while let Some(&(node, edge)) = call_stack.last() {
+                if let Some(&next) = self.successors[node].get(edge) {
+                    call_stack.last_mut().unwrap().1 += 1;
+                    match indices[next] {
+                        None => {
+                            indices[next] = Some(next_index);
+                            low_links[next] = next_index;
+                            next_index += 1;
+                            stack.push(next);
+                            on_stack[next] = true;
+                            call_stack.push((next, 0));
+                        }
+                        Some(index) if on_stack[next] => {
+                            low_links[node] = low_links[node].min(index);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                if let Some(&(parent, _)) = call_stack.last() {
+                    low_links[parent] = low_links[parent].min(low_links[node]);
+                }
+                if Some(low_links[node]) == indices[node] {
+                    let mut component = Vec::new();
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    // Returns the nodes of one cycle, in edge order, if there is one.
+    pub fn find_cycle(&self) -> Option<Vec<N>> {
+        let component = self
+            .component_ids()
+            .into_iter()
+            .find(|c| c.len() > 1 || self.successors[c[0]].contains(&c[0]))?;
+        let start = component[0];
+        let members = component.into_iter().collect::<HashSet<_>>();
+
+        // shortest way back to the start without leaving the component
+        let mut parents = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for &next in &self.successors[node] {
+                if !members.contains(&next) || parents.contains_key(&next) {
+                    continue;
+                }
+                parents.insert(next, node);
+                if next == start {
+                    queue.clear();
+                    break;
+                }
+                queue.push_back(next);
+            }
+        }
+
+        let mut cycle = vec![self.nodes[start].clone()];
+        let mut current = parents[&start];
+        while current != start {
+            cycle.push(self.nodes[current].clone());
+            current = parents[&current];
+        }
+        cycle[1..].reverse();
+        Some(cycle)
+    }
+
+    pub fn induced_subgraph<'a, I>(&self, nodes: I) -> DiGraph<N>
+    where
+        I: IntoIterator<Item = &'a N>,
+        N: 'a,
+    {
+        let mut subgraph = DiGraph::new();
+        nodes.into_iter().for_each(|node| {
+            subgraph.add_node(node.clone());
+        });
+        for from in 0..subgraph.len() {
+            let Some(original) = self.lookup.get(&subgraph.nodes[from]) else {
+                continue;
+            };
+            for to in &self.successors[*original] {
+                if let Some(to) = subgraph.lookup.get(&self.nodes[*to]) {
+                    subgraph.successors[from].push(*to);
+                }
+            }
+        }
+        subgraph
+    }
+
+    // Every node reachable from `start`, including itself.
+    pub fn reachable_from(&self, start: &N) -> HashSet<N> {
+        let Some(start) = self.lookup.get(start) else {
+            return HashSet::new();
+        };
+        let mut seen = HashSet::from([*start]);
+        let mut stack = vec![*start];
+        while let Some(node) = stack.pop() {
+            for next in &self.successors[node] {
+                if seen.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+        seen.into_iter().map(|id| self.nodes[id].clone()).collect()
+    }
+
+    pub fn is_reachable(&self, from: &N, to: &N) -> bool {
+        self.contains_node(to) && self.reachable_from(from).contains(to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::digraph::{CycleError, DiGraph};
+    use std::collections::HashSet;
+
+    #[test]
+    fn sorts_topologically() {
+        let graph = DiGraph::from_edges(&[(47, 53), (97, 13), (97, 47), (75, 47), (47, 13), (75, 53)]);
+        let order = graph.topological_sort().unwrap();
+        let position = |n| order.iter().position(|o| *o == n).unwrap();
+        assert_eq!(order.len(), 5);
+        assert!(position(97) < position(47));
+        assert!(position(47) < position(53));
+        assert!(position(47) < position(13));
+        assert!(position(75) < position(47));
+    }
+
+    #[test]
+    fn reports_cycle() {
+        let graph = DiGraph::from_edges(&[(1, 2), (2, 3), (3, 1), (3, 4)]);
+        let Err(CycleError { cycle }) = graph.topological_sort() else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle.len(), 3);
+        for (i, node) in cycle.iter().enumerate() {
+            assert!(graph.contains_edge(node, &cycle[(i + 1) % cycle.len()]));
+        }
+    }
+
+    #[test]
+    fn self_loop_is_a_cycle() {
+        let graph = DiGraph::from_edges(&[('a', 'a')]);
+        assert_eq!(graph.find_cycle(), Some(vec!['a']));
+    }
+
+    #[test]
+    fn finds_strongly_connected_components() {
+        let graph = DiGraph::from_edges(&[(1, 2), (2, 1), (2, 3), (3, 4), (4, 5), (5, 3), (6, 6)]);
+        let components = graph
+            .strongly_connected_components()
+            .into_iter()
+            .map(|c| c.into_iter().collect::<HashSet<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(components.len(), 3);
+        assert!(components.contains(&HashSet::from([1, 2])));
+        assert!(components.contains(&HashSet::from([3, 4, 5])));
+        assert!(components.contains(&HashSet::from([6])));
+        assert_eq!(DiGraph::from_edges(&[(1, 2)]).find_cycle(), None);
+    }
+
+    #[test]
+    fn induced_subgraph_breaks_cycles() {
+        let graph = DiGraph::from_edges(&[(1, 2), (2, 3), (3, 1)]);
+        let subgraph = graph.induced_subgraph(&[3, 2, 7]);
+        assert_eq!(subgraph.len(), 3);
+        assert!(subgraph.contains_edge(&2, &3));
+        assert!(!subgraph.contains_edge(&3, &1));
+        assert_eq!(subgraph.topological_sort(), Ok(vec![2, 7, 3]));
+    }
+
+    #[test]
+    fn reachability() {
+        let graph = DiGraph::from_edges(&[(1, 2), (2, 3), (4, 1)]);
+        assert_eq!(graph.reachable_from(&1), HashSet::from([1, 2, 3]));
+        assert!(graph.is_reachable(&4, &3));
+        assert!(!graph.is_reachable(&3, &4));
+        assert!(!graph.is_reachable(&1, &9));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::digraph::CycleError;
+use crate::linear_system::SolveError;
+use crate::tile::UnknownTileError;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::num::ParseIntError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AocError {
+    // the input doesn't have the expected shape
+    Parse(String),
+    // reading the input failed part way through, e.g. a streamed file that isn't UTF-8
+    Io(String),
+    // the input is well formed but the puzzle can't be solved on it, e.g. there's no guard
+    Domain(String),
+    Overflow,
+    // the solver ran out of the steps it was given, see budget::limit
+    OutOfSteps,
+    // the solver got into a state the puzzle rules shouldn't allow
+    Unreachable(String),
+}
+
+impl AocError {
+    pub fn parse(message: impl Into<String>) -> Self {
+        AocError::Parse(message.into())
+    }
+
+    pub fn domain(message: impl Into<String>) -> Self {
+        AocError::Domain(message.into())
+    }
+
+    pub fn unreachable(message: impl Into<String>) -> Self {
+        AocError::Unreachable(message.into())
+    }
+}
+
+impl Display for AocError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AocError::Parse(message) => write!(f, "invalid input: {}", message),
+            AocError::Io(message) => write!(f, "could not read input: {}", message),
+            AocError::Domain(message) => write!(f, "unsolvable input: {}", message),
+            AocError::Overflow => write!(f, "arithmetic overflow"),
+            AocError::OutOfSteps => write!(f, "ran out of steps"),
+            AocError::Unreachable(message) => write!(f, "unreachable state: {}", message),
+        }
+    }
+}
+
+impl Error for AocError {}
+
+impl From<ParseIntError> for AocError {
+    fn from(error: ParseIntError) -> Self {
+        AocError::Parse(error.to_string())
+    }
+}
+
+impl From<io::Error> for AocError {
+    fn from(error: io::Error) -> Self {
+        AocError::Io(error.to_string())
+    }
+}
+
+impl From<UnknownTileError> for AocError {
+    fn from(error: UnknownTileError) -> Self {
+        AocError::Parse(error.to_string())
+    }
+}
+
+impl<N: Debug> From<CycleError<N>> for AocError {
+    fn from(error: CycleError<N>) -> Self {
+        AocError::Domain(error.to_string())
+    }
+}
+
+impl From<SolveError> for AocError {
+    fn from(error: SolveError) -> Self {
+        match error {
+            SolveError::Overflow => AocError::Overflow,
+            SolveError::Shape => AocError::Unreachable(error.to_string()),
+        }
+    }
+}
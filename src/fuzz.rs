@@ -0,0 +1,328 @@
+// Fuzzing entry points, one per day. Each takes arbitrary bytes, parses them as that day's
+// input and runs both parts on whatever parses, all within a step budget. Errors are expected
+// for most inputs, the fuzz targets under fuzz/ only care about panics and hangs.
+use crate::budget;
+use crate::error::AocError;
+use crate::{
+    day_01, day_02, day_03, day_04, day_05, day_06, day_07, day_08, day_09, day_10, day_11,
+    day_12, day_13, day_14, day_15, day_16,
+};
+use crate::day_09::{Compress, Disk};
+use std::str;
+
+// Plenty for any input a fuzzer will come up with, small enough to fail fast on a runaway loop
+const STEPS: usize = 1_000_000;
+
+fn run(data: &[u8], solve: impl FnOnce(&str) -> Result<(), AocError>) -> Result<(), AocError> {
+    let input = str::from_utf8(data).map_err(|error| AocError::parse(error.to_string()))?;
+    budget::limit(STEPS, || solve(input))
+}
+
+pub fn day_01(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let [mut left, mut right] = day_01::parse_input(input)?;
+        day_01::get_similarity_score(&left, &right)?;
+        day_01::ListComparison::new(&left, &right)?;
+        day_01::find_sorted_differences_sum(&mut left, &mut right)?;
+        day_01::reconcile(input.as_bytes())?;
+        Ok(())
+    })
+}
+
+pub fn day_02(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let reports = day_02::parse_input(input)?;
+        day_02::find_number_of_safe_reports(&reports)?;
+        day_02::find_number_of_dampened_safe_reports(&reports)?;
+        Ok(())
+    })
+}
+
+pub fn day_03(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        day_03::get_computer_instructions_sum(input)?;
+        day_03::get_toggled_computer_instructions_sum(input)?;
+        day_03::read_toggled_computer_instructions_sum(input.as_bytes())?;
+        day_03::Interpreter::with_toggles().trace(input)?.to_json()?;
+        Ok(())
+    })
+}
+
+pub fn day_04(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let word_search = day_04::parse_input(input)?;
+        day_04::get_word_count("XMAS", &word_search)?;
+        day_04::get_cross_word_count(&word_search)?;
+        Ok(())
+    })
+}
+
+pub fn day_05(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let (ordering_rules, page_orders) = day_05::parse_input(input)?;
+        day_05::day_05(&ordering_rules, &page_orders)?;
+        day_05::day_05_hard(&ordering_rules, &page_orders)?;
+        Ok(())
+    })
+}
+
+pub fn day_06(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let lab = day_06::parse_input(input)?;
+        day_06::find_patrol_path(&lab)?;
+        day_06::find_obstructions(&lab)?;
+        Ok(())
+    })
+}
+
+pub fn day_07(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let mut equations = day_07::parse_input(input)?;
+        day_07::find_sum(&mut equations)?;
+        day_07::find_sum_hard(&mut equations)?;
+        Ok(())
+    })
+}
+
+pub fn day_08(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let antennas = day_08::parse_input(input)?;
+        day_08::find_all_anti_nodes(&antennas)?;
+        day_08::find_antinodes_hard(&antennas)?;
+        Ok(())
+    })
+}
+
+pub fn day_09(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let mut blocks = day_09::parse_input(input)?;
+        blocks.clone().compress_blocks()?;
+        blocks.compress_files()?;
+        let mut disk = Disk::parse(input)?;
+        disk.compress_files()?;
+        disk.checksum()?;
+        Ok(())
+    })
+}
+
+pub fn day_10(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let terrain = day_10::parse_input(input)?;
+        day_10::find_score(&terrain)?;
+        day_10::find_rating_score(&terrain)?;
+        Ok(())
+    })
+}
+
+pub fn day_11(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let stones = day_11::parse_input(input)?;
+        day_11::blink_stones(stones.clone(), 25)?;
+        day_11::blink_stones(stones, 75)?;
+        Ok(())
+    })
+}
+
+pub fn day_12(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let land = day_12::parse_input(input)?;
+        day_12::get_price(&land)?;
+        day_12::get_discounted_price(&land)?;
+        Ok(())
+    })
+}
+
+pub fn day_13(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let mut trials = day_13::parse_input(input)?;
+        day_13::fewest_tokens(&trials)?;
+        day_13::move_prizes(&mut trials, day_13::PRIZE_OFFSET)?;
+        day_13::fewest_tokens(&trials)?;
+        Ok(())
+    })
+}
+
+pub fn day_14(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let mut robots = day_14::parse_input(input)?;
+        day_14::find_alignment_time(&robots, &day_14::BATHROOM)?;
+        day_14::check_safety(&mut robots, 100, &day_14::BATHROOM)?;
+        Ok(())
+    })
+}
+
+pub fn day_15(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let (mut warehouse, instructions) = day_15::parse_input(input)?;
+        let mut wide_warehouse = warehouse.clone();
+        day_15::update_warehouse(&mut warehouse, &instructions)?;
+        day_15::count_score(&warehouse)?;
+        day_15::resize_warehouse(&mut wide_warehouse);
+        day_15::update_wide_warehouse(&mut wide_warehouse, &instructions)?;
+        day_15::count_wide_score(&wide_warehouse)?;
+        Ok(())
+    })
+}
+
+pub fn day_16(data: &[u8]) -> Result<(), AocError> {
+    run(data, |input| {
+        let maze = day_16::parse_input(input)?;
+        day_16::get_shortest_path_score(&maze)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget;
+    use crate::error::AocError;
+    use crate::fuzz;
+    use crate::parallel::Parallelism;
+    use crate::runner::{self, DAYS};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn known_crashers() {
+        assert_eq!(fuzz::day_09(b"2333133121414131402\n"), Ok(()));
+        assert!(fuzz::day_09(b"12a").is_err());
+        // an empty file and then only free space
+        assert_eq!(fuzz::day_09(b"06\n"), Ok(()));
+        assert_eq!(
+            fuzz::day_15(b"#@.#\n\n<x>"),
+            Err(AocError::parse("invalid instruction 'x'"))
+        );
+        assert!(fuzz::day_01(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn stops_runaway_solvers() {
+        // 3^20 ways to combine the numbers, far more than the budget allows
+        let line = format!("{}: {}", isize::MAX, ["1"; 21].join(" "));
+        assert_eq!(fuzz::day_07(line.as_bytes()), Err(AocError::OutOfSteps));
+    }
+
+    #[test]
+    fn every_solver_spends_steps() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        // day 4 reads each line of the grid once and has nowhere to stop
+        for day in DAYS.iter().filter(|day| day.number != 4) {
+            let input = fs::read_to_string(resources.join(day.input)).unwrap();
+            let sequential = Parallelism::sequential();
+            let left = budget::limit(1, || runner::solve(day.number, &input, sequential));
+            assert_eq!(left, Err(AocError::OutOfSteps), "day {}", day.number);
+        }
+    }
+
+    // Without checked arithmetic an overflow is a panic in debug builds, which is why the fuzz
+    // targets turn the feature on
+    #[cfg(feature = "checked-arithmetic")]
+    mod mutated {
+        use crate::error::AocError;
+        use crate::fuzz;
+        use crate::generators::{self, Rng};
+
+        type Target = fn(&[u8]) -> Result<(), AocError>;
+        type Generator = fn(u64, usize) -> String;
+
+        // Small corruptions of a valid input: flipped, dropped and repeated bytes, and
+        // characters the parsers have to deal with
+        fn mutate(rng: &mut Rng, input: &str) -> Vec<u8> {
+            let mut bytes = input.as_bytes().to_vec();
+            for _ in 0..rng.below(4) {
+                if bytes.is_empty() {
+                    break;
+                }
+                let at = rng.below(bytes.len());
+                match rng.below(4) {
+                    0 => bytes[at] = *rng.choose(b"\n -+:|,.#@^<>v0123456789"),
+                    1 => {
+                        bytes.remove(at);
+                    }
+                    2 => bytes.insert(at, bytes[at]),
+                    _ => bytes.truncate(at),
+                }
+            }
+            bytes
+        }
+
+        // Numbers near the edges of isize and u64, and their negatives, where the overflow
+        // paths are
+        const MAGNITUDES: [&str; 10] = [
+            "0",
+            "-1",
+            "3037000500",
+            "-3037000500",
+            "4611686018427387904",
+            "9000000000000000000",
+            "-9000000000000000000",
+            "9223372036854775807",
+            "-9223372036854775808",
+            "18446744073709551615",
+        ];
+
+        // A valid input with some of its numbers swapped for one of the magnitudes above
+        fn magnify(rng: &mut Rng, input: &str) -> Vec<u8> {
+            let mut magnified = String::new();
+            let mut rest = input;
+            while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+                let end = rest[start..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .map_or(rest.len(), |length| start + length);
+                magnified.push_str(&rest[..start]);
+                match rng.one_in(3) {
+                    true => magnified.push_str(MAGNITUDES[rng.below(MAGNITUDES.len())]),
+                    false => magnified.push_str(&rest[start..end]),
+                }
+                rest = &rest[end..];
+            }
+            magnified.push_str(rest);
+            magnified.into_bytes()
+        }
+
+        fn survives(target: Target, generate: Generator, cases: u64) {
+            let mut rng = Rng::new(0);
+            for seed in 0..cases {
+                let input = generate(seed, 1 + seed as usize % 8);
+                let _ = target(input.as_bytes());
+                let _ = target(&mutate(&mut rng, &input));
+                let _ = target(&magnify(&mut rng, &input));
+            }
+        }
+
+        #[test]
+        fn overflow_regressions() {
+            let calibration = b"0: -9000000000000000000 9000000000000000000 9000000000000000000";
+            assert_eq!(fuzz::day_07(calibration), Err(AocError::Overflow));
+            let machine = "Button A: X+1, Y+0\nButton B: X+0, Y+1\nPrize: X=3000000000000000000, Y=0";
+            let machines = format!("{}\n\n{}", machine, machine);
+            assert_eq!(fuzz::day_13(machines.as_bytes()), Err(AocError::Overflow));
+        }
+
+        #[test]
+        fn survives_mutated_inputs() {
+            let targets: [(Target, Generator); 15] = [
+                (fuzz::day_01, generators::location_lists),
+                (fuzz::day_02, generators::level_reports),
+                (fuzz::day_03, generators::corrupted_memory),
+                (fuzz::day_04, generators::word_search),
+                (fuzz::day_05, generators::page_rules),
+                (fuzz::day_06, generators::lab),
+                (fuzz::day_07, generators::calibration_lines),
+                (fuzz::day_08, generators::antenna_map),
+                (fuzz::day_09, generators::disk_map),
+                (fuzz::day_10, generators::topographic_map),
+                (fuzz::day_12, generators::garden),
+                (fuzz::day_13, generators::claw_machines),
+                (fuzz::day_14, generators::robots),
+                (fuzz::day_15, generators::warehouse),
+                (fuzz::day_16, generators::reindeer_maze),
+            ];
+            for (target, generate) in targets {
+                survives(target, generate, 300);
+            }
+            // 75 blinks take a while in a debug build
+            survives(fuzz::day_11, generators::stones, 10);
+        }
+    }
+}
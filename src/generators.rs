@@ -0,0 +1,492 @@
+// Seeded generators for puzzle-shaped inputs, for benchmarks and fuzzing. Every generator
+// takes a seed and a size and returns the input text in the same format as the files under
+// resources/, so the same seed always gives the same input.
+use std::collections::HashSet;
+
+// SplitMix64, small and good enough to spread test inputs around
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in 0..n, n has to be positive
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "can't pick below zero");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Uniform in low..=high
+    pub fn between(&mut self, low: isize, high: isize) -> isize {
+        assert!(low <= high, "empty range {}..={}", low, high);
+        low + (self.next_u64() % (high - low + 1) as u64) as isize
+    }
+
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+const DIRECTIONS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+fn render(grid: &[Vec<char>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn step(grid: &[Vec<char>], (x, y): (usize, usize), (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+    let x = x.checked_add_signed(dx)?;
+    let y = y.checked_add_signed(dy)?;
+    (y < grid.len() && x < grid[y].len()).then_some((x, y))
+}
+
+// Day 1: `size` lines of two five digit location ids
+pub fn location_lists(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| format!("{}   {}", rng.between(10000, 99999), rng.between(10000, 99999)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Day 2: `size` reports, most of them steady runs with the odd bad level thrown in
+pub fn level_reports(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| {
+            let direction = if rng.one_in(2) { 1 } else { -1 };
+            let mut level = rng.between(10, 90);
+            let mut levels = vec![level];
+            for _ in 1..rng.between(5, 8) {
+                let change = if rng.one_in(6) {
+                    rng.between(-4, 4)
+                } else {
+                    direction * rng.between(1, 3)
+                };
+                level = (level + change).max(1);
+                levels.push(level);
+            }
+            levels
+                .iter()
+                .map(|level| level.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Day 3: `size` fragments of corrupted memory, a mix of real instructions, near misses and noise
+pub fn corrupted_memory(seed: u64, size: usize) -> String {
+    const NOISE: &[char] = &[
+        'm', 'u', 'l', '(', ')', ',', 'd', 'o', 'n', '\'', 't', '!', '@', '#', '$', '%', '^',
+        '&', '*', '[', ']', '<', '>', '?', ' ', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+    ];
+    let mut rng = Rng::new(seed);
+    let mut memory = String::new();
+    for _ in 0..size {
+        match rng.below(8) {
+            0..=2 => memory += &format!("mul({},{})", rng.between(0, 999), rng.between(0, 999)),
+            3 => memory += "do()",
+            4 => memory += "don't()",
+            5 => {
+                let near_miss = [
+                    format!("mul({}*", rng.between(1, 999)),
+                    format!("mul ( {} , {} )", rng.between(1, 99), rng.between(1, 99)),
+                    format!("mul({},{})", rng.between(1000, 9999), rng.between(1, 999)),
+                    format!("mul[{},{}]", rng.between(1, 999), rng.between(1, 999)),
+                    "do_not()".to_string(),
+                ];
+                memory += rng.choose(&near_miss).as_str();
+            }
+            _ => (0..rng.between(1, 8)).for_each(|_| memory.push(*rng.choose(NOISE))),
+        }
+    }
+    memory
+}
+
+// Day 4: a `size` by `size` grid of X, M, A and S
+pub fn word_search(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    let grid = (0..size)
+        .map(|_| (0..size).map(|_| *rng.choose(&['X', 'M', 'A', 'S'])).collect())
+        .collect::<Vec<_>>();
+    render(&grid)
+}
+
+// Day 5: rules that put `size` pages (at most 90) in one hidden order, then `size` updates of
+// which roughly half are already in that order
+pub fn page_rules(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    let mut pages = (10..100).collect::<Vec<usize>>();
+    rng.shuffle(&mut pages);
+    pages.truncate(size.clamp(3, 90));
+
+    let mut rules = Vec::new();
+    for (i, before) in pages.iter().enumerate() {
+        for after in &pages[i + 1..] {
+            rules.push(format!("{}|{}", before, after));
+        }
+    }
+    rng.shuffle(&mut rules);
+
+    let updates = (0..size)
+        .map(|_| {
+            let length = (rng.below(pages.len().min(23) / 2) * 2 + 1).max(3).min(pages.len());
+            let mut update = pages.clone();
+            rng.shuffle(&mut update);
+            update.truncate(length);
+            if rng.one_in(2) {
+                update.sort_by_key(|page| pages.iter().position(|p| p == page));
+            }
+            update
+                .iter()
+                .map(|page| page.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}\n\n{}", rules.join("\n"), updates.join("\n"))
+}
+
+// Day 6: a `size` by `size` lab (at least 3 wide) with a guard that walks out of it in the end
+pub fn lab(seed: u64, size: usize) -> String {
+    let size = size.max(3);
+    let mut rng = Rng::new(seed);
+    loop {
+        let mut grid = (0..size)
+            .map(|_| {
+                (0..size)
+                    .map(|_| if rng.one_in(8) { '#' } else { '.' })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let guard = (rng.below(size), rng.below(size));
+        grid[guard.1][guard.0] = '^';
+        if guard_leaves(&grid, guard) {
+            return render(&grid);
+        }
+    }
+}
+
+fn guard_leaves(grid: &[Vec<char>], start: (usize, usize)) -> bool {
+    let mut seen = HashSet::new();
+    let (mut position, mut direction) = (start, 0);
+    while seen.insert((position, direction)) {
+        match step(grid, position, DIRECTIONS[direction]) {
+            None => return true,
+            Some((x, y)) if grid[y][x] == '#' => direction = (direction + 1) % 4,
+            Some(next) => position = next,
+        }
+    }
+    false
+}
+
+// Day 7: `size` calibration lines. About half of them add up with +, * and || on their
+// numbers, the rest have a random test value.
+pub fn calibration_lines(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| {
+            let numbers = (0..rng.between(2, 6))
+                .map(|_| rng.between(1, 99))
+                .collect::<Vec<_>>();
+            let expected = if rng.one_in(2) {
+                numbers[1..].iter().fold(numbers[0], |total, number| match rng.below(3) {
+                    0 => total + number,
+                    1 => total * number,
+                    _ => format!("{}{}", total, number).parse().unwrap(),
+                })
+            } else {
+                rng.between(1, 1_000_000)
+            };
+            let numbers = numbers
+                .iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{}: {}", expected, numbers)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Day 8: a `size` by `size` map with a handful of antennas on each of a few frequencies
+pub fn antenna_map(seed: u64, size: usize) -> String {
+    const FREQUENCIES: &[char] = &['a', 'b', 'A', 'B', '0', '1', 'z', 'Z', '9'];
+    let mut rng = Rng::new(seed);
+    let mut grid = vec![vec!['.'; size]; size];
+    if size == 0 {
+        return String::new();
+    }
+    for _ in 0..(size * size / 20).max(2) {
+        grid[rng.below(size)][rng.below(size)] = *rng.choose(FREQUENCIES);
+    }
+    render(&grid)
+}
+
+// Day 9: a disk map of `size` files with free space between them
+pub fn disk_map(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    let mut map = String::new();
+    for file in 0..size {
+        if file > 0 {
+            map += &rng.between(0, 9).to_string();
+        }
+        map += &rng.between(1, 9).to_string();
+    }
+    map
+}
+
+// Day 10: a `size` by `size` height map that mostly climbs towards the bottom right, so it
+// has plenty of trails
+pub fn topographic_map(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    let grid = (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| {
+                    let height = (x + y + rng.below(2)) % 10;
+                    char::from_digit(height as u32, 10).unwrap()
+                })
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    render(&grid)
+}
+
+// Day 11: `size` stones
+pub fn stones(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| match rng.below(3) {
+            0 => rng.between(0, 9).to_string(),
+            _ => rng.between(0, 9_999_999).to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Day 12: a `size` by `size` garden of plots that clump together into regions
+pub fn garden(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    let mut grid: Vec<Vec<char>> = Vec::new();
+    for y in 0..size {
+        let mut row: Vec<char> = Vec::new();
+        for x in 0..size {
+            let plant = match rng.below(5) {
+                0 | 1 if x > 0 => row[x - 1],
+                2 | 3 if y > 0 => grid[y - 1][x],
+                _ => *rng.choose(&['A', 'B', 'C', 'D', 'E']),
+            };
+            row.push(plant);
+        }
+        grid.push(row);
+    }
+    render(&grid)
+}
+
+// Day 13: `size` claw machines, about half of which can be won with at most 100 presses
+pub fn claw_machines(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| {
+            let (ax, ay) = (rng.between(10, 99), rng.between(10, 99));
+            let (bx, by) = (rng.between(10, 99), rng.between(10, 99));
+            let (px, py) = if rng.one_in(2) {
+                let (a, b) = (rng.between(0, 100), rng.between(0, 100));
+                (a * ax + b * bx, a * ay + b * by)
+            } else {
+                (rng.between(100, 20000), rng.between(100, 20000))
+            };
+            format!(
+                "Button A: X+{}, Y+{}\nButton B: X+{}, Y+{}\nPrize: X={}, Y={}",
+                ax, ay, bx, by, px, py
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Day 14: `size` robots in the 101 by 103 room
+pub fn robots(seed: u64, size: usize) -> String {
+    let mut rng = Rng::new(seed);
+    (0..size)
+        .map(|_| {
+            format!(
+                "p={},{} v={},{}",
+                rng.between(0, 100),
+                rng.between(0, 102),
+                rng.between(-100, 100),
+                rng.between(-102, 102)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Day 15: a walled `size` by `size` warehouse (at least 4 wide) with boxes and one robot,
+// followed by `size` lines of moves
+pub fn warehouse(seed: u64, size: usize) -> String {
+    let size = size.max(4);
+    let mut rng = Rng::new(seed);
+    let mut grid = (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| {
+                    if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                        '#'
+                    } else {
+                        match rng.below(10) {
+                            0 => '#',
+                            1..=3 => 'O',
+                            _ => '.',
+                        }
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    grid[rng.below(size - 2) + 1][rng.below(size - 2) + 1] = '@';
+
+    let moves = (0..size)
+        .map(|_| (0..70).map(|_| *rng.choose(&['^', '>', 'v', '<'])).collect::<String>())
+        .collect::<Vec<_>>();
+    format!("{}\n\n{}", render(&grid), moves.join("\n"))
+}
+
+// Day 16: a walled `size` by `size` maze (at least 4 wide) with S in the bottom left and E
+// in the top right, always joined by at least one path
+pub fn reindeer_maze(seed: u64, size: usize) -> String {
+    let size = size.max(4);
+    let mut rng = Rng::new(seed);
+    let mut grid = (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| {
+                    let border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                    if border || rng.one_in(3) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // carve a staircase from S to E so the end can always be reached
+    let (mut x, mut y) = (1, size - 2);
+    while (x, y) != (size - 2, 1) {
+        grid[y][x] = '.';
+        if y == 1 || (x < size - 2 && rng.one_in(2)) {
+            x += 1;
+        } else {
+            y -= 1;
+        }
+    }
+    grid[size - 2][1] = 'S';
+    grid[1][size - 2] = 'E';
+    render(&grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators::{
+        antenna_map, calibration_lines, claw_machines, corrupted_memory, disk_map, garden,
+        guard_leaves, lab, level_reports, location_lists, page_rules, reindeer_maze, robots,
+        stones, topographic_map, warehouse, word_search, Rng,
+    };
+
+    #[test]
+    fn same_seed_same_input() {
+        assert_eq!(lab(7, 20), lab(7, 20));
+        assert_eq!(corrupted_memory(7, 50), corrupted_memory(7, 50));
+        assert_ne!(reindeer_maze(7, 20), reindeer_maze(8, 20));
+    }
+
+    #[test]
+    fn grids_have_the_requested_size() {
+        for grid in [
+            word_search(1, 12),
+            lab(1, 12),
+            antenna_map(1, 12),
+            topographic_map(1, 12),
+            garden(1, 12),
+            reindeer_maze(1, 12),
+        ] {
+            let lines = grid.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 12);
+            assert!(lines.iter().all(|line| line.chars().count() == 12));
+        }
+        let warehouse = warehouse(1, 12);
+        let (grid, moves) = warehouse.split_once("\n\n").unwrap();
+        assert_eq!(grid.lines().count(), 12);
+        assert_eq!(grid.matches('@').count(), 1);
+        assert_eq!(moves.lines().count(), 12);
+    }
+
+    #[test]
+    fn lists_have_the_requested_length() {
+        assert_eq!(location_lists(1, 25).lines().count(), 25);
+        assert_eq!(level_reports(1, 25).lines().count(), 25);
+        assert_eq!(calibration_lines(1, 25).lines().count(), 25);
+        assert_eq!(robots(1, 25).lines().count(), 25);
+        assert_eq!(stones(1, 25).split(' ').count(), 25);
+        assert_eq!(disk_map(1, 25).len(), 49);
+        assert_eq!(claw_machines(1, 25).split("\n\n").count(), 25);
+        let rules = page_rules(1, 25);
+        let (rules, updates) = rules.split_once("\n\n").unwrap();
+        assert_eq!(rules.lines().count(), 25 * 24 / 2);
+        assert_eq!(updates.lines().count(), 25);
+    }
+
+    #[test]
+    fn guards_always_leave() {
+        for seed in 0..50 {
+            let lab = lab(seed, 10);
+            let grid = lab.lines().map(|line| line.chars().collect()).collect::<Vec<Vec<_>>>();
+            let y = grid.iter().position(|row| row.contains(&'^')).unwrap();
+            let x = grid[y].iter().position(|ch| *ch == '^').unwrap();
+            assert!(guard_leaves(&grid, (x, y)));
+        }
+    }
+
+    #[test]
+    fn rng_stays_in_range() {
+        let mut rng = Rng::new(42);
+        for _ in 0..1000 {
+            assert!(rng.below(7) < 7);
+            assert!((-3..=3).contains(&rng.between(-3, 3)));
+        }
+        let mut items = (0..10).collect::<Vec<_>>();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+}
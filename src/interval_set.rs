@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+use std::ops::Range;
+
+// Ordered, non-overlapping half-open ranges. Touching ranges are merged, so every span is
+// separated from the next by a gap of at least one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    spans: BTreeMap<usize, usize>,
+    // the start of every gap before the last span, by the gap's length
+    gaps: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn from_spans(spans: BTreeMap<usize, usize>) -> Self {
+        let mut set = IntervalSet {
+            spans,
+            gaps: BTreeMap::new(),
+        };
+        let gaps = set.gaps().collect::<Vec<_>>();
+        gaps.into_iter()
+            .for_each(|gap| set.link_gap(gap.start, gap.end));
+        set
+    }
+
+    fn link_gap(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.gaps.entry(end - start).or_default().insert(start);
+        }
+    }
+
+    fn unlink_gap(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        if let Some(starts) = self.gaps.get_mut(&(end - start)) {
+            starts.remove(&start);
+            if starts.is_empty() {
+                self.gaps.remove(&(end - start));
+            }
+        }
+    }
+
+    // End of the last span before `point`, or zero
+    fn end_before(&self, point: usize) -> usize {
+        self.spans
+            .range(..point)
+            .next_back()
+            .map_or(0, |(_, end)| *end)
+    }
+
+    fn start_after(&self, point: usize) -> Option<usize> {
+        self.spans.range(point..).next().map(|(start, _)| *start)
+    }
+
+    // Adds a span that neither overlaps nor touches any other, splitting the gap it lands in
+    fn add_span(&mut self, start: usize, end: usize) {
+        let before = self.end_before(start);
+        if let Some(after) = self.start_after(end) {
+            self.unlink_gap(before, after);
+            self.link_gap(end, after);
+        }
+        self.link_gap(before, start);
+        self.spans.insert(start, end);
+    }
+
+    // Takes out the span starting at `start`, joining the gaps either side of it
+    fn remove_span(&mut self, start: usize) {
+        let Some(end) = self.spans.remove(&start) else {
+            return;
+        };
+        let before = self.end_before(start);
+        self.unlink_gap(before, start);
+        if let Some(after) = self.start_after(end) {
+            self.unlink_gap(end, after);
+            self.link_gap(before, after);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    // Total number of points covered by the spans
+    pub fn coverage(&self) -> usize {
+        self.spans.iter().map(|(start, end)| end - start).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.spans.iter().map(|(start, end)| *start..*end)
+    }
+
+    pub fn contains(&self, point: usize) -> bool {
+        self.span_containing(point).is_some()
+    }
+
+    pub fn span_containing(&self, point: usize) -> Option<Range<usize>> {
+        let (start, end) = self.spans.range(..=point).next_back()?;
+        (point < *end).then_some(*start..*end)
+    }
+
+    pub fn insert(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let mut start = range.start;
+        let mut end = range.end;
+
+        // swallow a span that reaches into or touches the new one from the left
+        if let Some((&left_start, &left_end)) = self.spans.range(..=start).next_back() {
+            if left_end >= start {
+                start = left_start;
+                end = end.max(left_end);
+                self.remove_span(left_start);
+            }
+        }
+        // and every span that starts inside or right after it
+        let swallowed = self
+            .spans
+            .range(start..=end)
+            .map(|(start, end)| (*start, *end))
+            .collect::<Vec<_>>();
+        for (swallowed_start, swallowed_end) in swallowed {
+            end = end.max(swallowed_end);
+            self.remove_span(swallowed_start);
+        }
+        self.add_span(start, end);
+    }
+
+    pub fn remove(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let overlapping = self
+            .spans
+            .range(..range.end)
+            .rev()
+            .take_while(|(_, end)| **end > range.start)
+            .map(|(start, end)| (*start, *end))
+            .collect::<Vec<_>>();
+        for (start, end) in overlapping {
+            self.remove_span(start);
+            if start < range.start {
+                self.add_span(start, range.start);
+            }
+            if end > range.end {
+                self.add_span(range.end, end);
+            }
+        }
+    }
+
+    // Adds every span of `other`
+    pub fn merge(&mut self, other: &IntervalSet) {
+        other.iter().for_each(|range| self.insert(range));
+    }
+
+    // Splits the set at `point`, keeping everything below it and returning the rest.
+    pub fn split_off(&mut self, point: usize) -> IntervalSet {
+        let mut upper = self.spans.split_off(&point);
+        if let Some((_, end)) = self.spans.iter_mut().next_back() {
+            if *end > point {
+                upper.insert(point, *end);
+                *end = point;
+            }
+        }
+        *self = IntervalSet::from_spans(mem::take(&mut self.spans));
+        IntervalSet::from_spans(upper)
+    }
+
+    // Uncovered ranges from zero up to the last span
+    pub fn gaps(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let mut previous_end = 0;
+        self.spans.iter().filter_map(move |(start, end)| {
+            let gap = previous_end..*start;
+            previous_end = *end;
+            (!gap.is_empty()).then_some(gap)
+        })
+    }
+
+    // Start of the leftmost uncovered run of at least `length` points that ends at or before
+    // `before`. Space past the last span counts as uncovered. Looks at the first gap of each
+    // length that's long enough rather than at every span.
+    pub fn first_gap(&self, length: usize, before: usize) -> Option<usize> {
+        if length == 0 {
+            return Some(0);
+        }
+        // the leftmost gap long enough is the only one that can fit before `before`, and the
+        // space past the last span is right of all of them
+        let start = self
+            .gaps
+            .range(length..)
+            .filter_map(|(_, starts)| starts.first())
+            .min()
+            .copied()
+            .unwrap_or_else(|| self.spans.values().next_back().map_or(0, |end| *end));
+        start
+            .checked_add(length)
+            .is_some_and(|end| end <= before)
+            .then_some(start)
+    }
+}
+
+impl FromIterator<Range<usize>> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Range<usize>>>(iter: T) -> Self {
+        let mut set = IntervalSet::new();
+        iter.into_iter().for_each(|range| set.insert(range));
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generators::Rng;
+    use crate::interval_set::IntervalSet;
+    use std::iter;
+    use std::ops::Range;
+
+    fn spans(set: &IntervalSet) -> Vec<Range<usize>> {
+        set.iter().collect()
+    }
+
+    #[test]
+    fn insert_merges_overlapping_and_touching() {
+        let mut set = IntervalSet::from_iter([0..2, 5..7, 10..12]);
+        assert_eq!(set.len(), 3);
+        set.insert(2..3);
+        assert_eq!(spans(&set), vec![0..3, 5..7, 10..12]);
+        set.insert(6..11);
+        assert_eq!(spans(&set), vec![0..3, 5..12]);
+        set.insert(4..4);
+        assert_eq!(spans(&set), vec![0..3, 5..12]);
+        set.insert(1..20);
+        assert_eq!(spans(&set), vec![0..20]);
+    }
+
+    #[test]
+    fn remove_splits() {
+        let mut set = IntervalSet::from_iter([0..10, 20..30]);
+        set.remove(3..5);
+        assert_eq!(spans(&set), vec![0..3, 5..10, 20..30]);
+        set.remove(8..25);
+        assert_eq!(spans(&set), vec![0..3, 5..8, 25..30]);
+        set.remove(0..100);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn membership_and_coverage() {
+        let set = IntervalSet::from_iter([2..5, 8..9]);
+        assert!(set.contains(2));
+        assert!(set.contains(4));
+        assert!(!set.contains(5));
+        assert!(!set.contains(0));
+        assert_eq!(set.span_containing(8), Some(8..9));
+        assert_eq!(set.coverage(), 4);
+        assert_eq!(set.gaps().collect::<Vec<_>>(), vec![0..2, 5..8]);
+    }
+
+    #[test]
+    fn merge_and_split() {
+        let mut set = IntervalSet::from_iter([0..4, 10..14]);
+        set.merge(&IntervalSet::from_iter([4..6, 12..20]));
+        assert_eq!(spans(&set), vec![0..6, 10..20]);
+        let upper = set.split_off(12);
+        assert_eq!(spans(&set), vec![0..6, 10..12]);
+        assert_eq!(spans(&upper), vec![12..20]);
+    }
+
+    #[test]
+    fn first_gap() {
+        let set = IntervalSet::from_iter([0..2, 3..5, 8..10]);
+        assert_eq!(set.first_gap(1, 10), Some(2));
+        assert_eq!(set.first_gap(2, 10), Some(5));
+        assert_eq!(set.first_gap(3, 10), Some(5));
+        assert_eq!(set.first_gap(3, 7), None);
+        assert_eq!(set.first_gap(4, 20), Some(10));
+        assert_eq!(set.first_gap(4, 13), None);
+        assert_eq!(set.first_gap(0, 0), Some(0));
+    }
+
+    // Walks every gap in order, as first_gap did before it had an index
+    fn scan_for_gap(set: &IntervalSet, length: usize, before: usize) -> Option<usize> {
+        let tail = set.iter().last().map_or(0, |span| span.end);
+        set.gaps()
+            .chain(iter::once(tail..usize::MAX))
+            .find(|gap| gap.start + length <= gap.end.min(before) || gap.start >= before)
+            .map(|gap| gap.start)
+            .filter(|start| start + length <= before)
+    }
+
+    #[test]
+    fn first_gap_over_many_spans() {
+        let mut set = IntervalSet::new();
+        let mut rng = Rng::new(7);
+        let mut next = |n: usize| rng.below(n);
+        for round in 0..4000 {
+            let start = next(20_000);
+            let range = start..start + 1 + next(9);
+            match round % 3 {
+                2 => set.remove(range),
+                _ => set.insert(range),
+            }
+            let (length, before) = (1 + next(12), next(21_000));
+            assert_eq!(
+                set.first_gap(length, before),
+                scan_for_gap(&set, length, before)
+            );
+        }
+        assert!(set.len() > 500);
+        for length in 1..15 {
+            for before in (0..21_000).step_by(97) {
+                assert_eq!(
+                    set.first_gap(length, before),
+                    scan_for_gap(&set, length, before)
+                );
+            }
+        }
+        let upper = set.split_off(10_000);
+        for length in 1..15 {
+            assert_eq!(
+                set.first_gap(length, 12_000),
+                scan_for_gap(&set, length, 12_000)
+            );
+            assert_eq!(
+                upper.first_gap(length, 15_000),
+                scan_for_gap(&upper, length, 15_000)
+            );
+        }
+    }
+}
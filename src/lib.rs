@@ -18,3 +18,22 @@ mod day_13;
 mod day_14;
 mod day_15;
 mod day_16;
+mod tile;
+mod bitset;
+mod union_find;
+mod digraph;
+mod cycle;
+mod number_theory;
+mod rational;
+mod linear_system;
+mod interval_set;
+mod memo;
+pub mod error;
+mod arith;
+pub mod generators;
+mod budget;
+pub mod fuzz;
+#[cfg(test)]
+mod differential;
+pub mod parallel;
+pub mod runner;
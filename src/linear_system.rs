@@ -0,0 +1,189 @@
+use crate::rational::Rational;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Solution {
+    Unique(Vec<Rational>),
+    None,
+    // Every solution is particular + t1 * basis[0] + t2 * basis[1] + ... for any t1, t2, ...
+    Infinite {
+        particular: Vec<Rational>,
+        basis: Vec<Vec<Rational>>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    Overflow,
+    Shape,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::Overflow => write!(f, "arithmetic overflow while solving"),
+            SolveError::Shape => write!(f, "coefficients and constants don't line up"),
+        }
+    }
+}
+
+impl Error for SolveError {}
+
+fn checked<T>(value: Option<T>) -> Result<T, SolveError> {
+    value.ok_or(SolveError::Overflow)
+}
+
+// Solves coefficients * x = constants exactly, by reducing the augmented matrix to reduced
+// row echelon form.
+pub fn solve(coefficients: &[Vec<i128>], constants: &[i128]) -> Result<Solution, SolveError> {
+    let n_rows = coefficients.len();
+    let n_columns = coefficients.first().map_or(0, Vec::len);
+    if constants.len() != n_rows || coefficients.iter().any(|row| row.len() != n_columns) {
+        return Err(SolveError::Shape);
+    }
+
+    let mut matrix = coefficients
+        .iter()
+        .zip(constants)
+        .map(|(row, constant)| {
+            row.iter()
+                .chain([constant])
+                .map(|value| Rational::from(*value))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut pivot_columns = Vec::new();
+    let mut pivot_row = 0;
+    for column in 0..n_columns {
+        let Some(found) = (pivot_row..n_rows).find(|row| !matrix[*row][column].is_zero()) else {
+            continue;
+        };
+        matrix.swap(pivot_row, found);
+
+        let pivot = matrix[pivot_row][column];
+        for value in matrix[pivot_row].iter_mut() {
+            *value = checked(value.checked_div(pivot))?;
+        }
+        let pivot_values = matrix[pivot_row].clone();
+        for (row, values) in matrix.iter_mut().enumerate() {
+            let factor = values[column];
+            if row == pivot_row || factor.is_zero() {
+                continue;
+            }
+            for (value, pivot_value) in values.iter_mut().zip(&pivot_values).skip(column) {
+                let scaled = checked(pivot_value.checked_mul(factor))?;
+                *value = checked(value.checked_sub(scaled))?;
+            }
+        }
+
+        pivot_columns.push(column);
+        pivot_row += 1;
+        if pivot_row == n_rows {
+            break;
+        }
+    }
+
+    // a row of zeros that still has to equal something
+    if matrix[pivot_row..].iter().any(|row| !row[n_columns].is_zero()) {
+        return Ok(Solution::None);
+    }
+
+    let mut particular = vec![Rational::ZERO; n_columns];
+    for (row, column) in pivot_columns.iter().enumerate() {
+        particular[*column] = matrix[row][n_columns];
+    }
+    if pivot_columns.len() == n_columns {
+        return Ok(Solution::Unique(particular));
+    }
+
+    let mut basis = Vec::new();
+    for free in (0..n_columns).filter(|column| !pivot_columns.contains(column)) {
+        let mut direction = vec![Rational::ZERO; n_columns];
+        direction[free] = Rational::ONE;
+        for (row, column) in pivot_columns.iter().enumerate() {
+            direction[*column] = checked(matrix[row][free].checked_neg())?;
+        }
+        basis.push(direction);
+    }
+    Ok(Solution::Infinite { particular, basis })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::linear_system::{solve, Solution, SolveError};
+    use crate::rational::Rational;
+
+    fn integers(values: &[i128]) -> Vec<Rational> {
+        values.iter().map(|value| Rational::from(*value)).collect()
+    }
+
+    fn multiply(coefficients: &[Vec<i128>], x: &[Rational]) -> Vec<Rational> {
+        coefficients
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(x)
+                    .fold(Rational::ZERO, |sum, (a, x)| sum + Rational::from(*a) * *x)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unique_solution() {
+        let coefficients = vec![vec![94, 22], vec![34, 67]];
+        let left = solve(&coefficients, &[8400, 5400]);
+        let right = Ok(Solution::Unique(integers(&[80, 40])));
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn unique_fractional_solution() {
+        let left = solve(&[vec![2, 0], vec![0, 3]], &[1, 1]);
+        let right = Ok(Solution::Unique(vec![
+            Rational::new(1, 2).unwrap(),
+            Rational::new(1, 3).unwrap(),
+        ]));
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn large_offsets_stay_exact() {
+        let coefficients = vec![vec![26, 67], vec![66, 21]];
+        let constants = [10_000_000_012_748, 10_000_000_012_176];
+        let Ok(Solution::Unique(x)) = solve(&coefficients, &constants) else {
+            panic!("expected a unique solution");
+        };
+        assert_eq!(x, integers(&[118679050709, 103199174542]));
+    }
+
+    #[test]
+    fn no_solution() {
+        let left = solve(&[vec![1, 2], vec![2, 4]], &[3, 7]);
+        assert_eq!(left, Ok(Solution::None));
+    }
+
+    #[test]
+    fn infinite_solutions() {
+        let coefficients = vec![vec![1, 2, 3], vec![2, 4, 6], vec![1, 0, 1]];
+        let constants = [6, 12, 2];
+        let Ok(Solution::Infinite { particular, basis }) = solve(&coefficients, &constants) else {
+            panic!("expected infinitely many solutions");
+        };
+        assert_eq!(basis.len(), 1);
+        assert_eq!(multiply(&coefficients, &particular), integers(&constants));
+        let shifted = particular
+            .iter()
+            .zip(&basis[0])
+            .map(|(p, b)| *p + Rational::from(7) * *b)
+            .collect::<Vec<_>>();
+        assert_eq!(multiply(&coefficients, &shifted), integers(&constants));
+    }
+
+    #[test]
+    fn rejects_bad_shapes() {
+        assert_eq!(solve(&[vec![1, 2], vec![1]], &[1, 2]), Err(SolveError::Shape));
+        assert_eq!(solve(&[vec![1, 2]], &[1, 2]), Err(SolveError::Shape));
+    }
+}
@@ -0,0 +1,73 @@
+// `aoc run --all` solves every day from its puzzle input under resources/, `aoc run --day N`
+// just the one
+use aoc_2024_rust::parallel::Parallelism;
+use aoc_2024_rust::runner::{self, DAYS};
+use clap::{Parser, Subcommand};
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(name = "aoc", about = "Advent of Code 2024 solutions")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve days and print their answers
+    Run {
+        /// Every day that has a solver
+        #[arg(long, required_unless_present = "day", conflicts_with = "day")]
+        all: bool,
+        /// Only this day
+        #[arg(long)]
+        day: Option<u8>,
+        /// Worker threads for the days that can split their work up, 0 for one per core
+        #[arg(long, default_value_t = 1)]
+        workers: usize,
+        /// Where the puzzle inputs are
+        #[arg(long, default_value = "resources")]
+        resources: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let Command::Run { day, workers, resources, .. } = Cli::parse().command;
+    let parallelism = match workers {
+        0 => Parallelism::available(),
+        workers => Parallelism::with_workers(workers),
+    };
+    if let Some(day) = day.filter(|day| DAYS.iter().all(|known| known.number != *day)) {
+        eprintln!("there's no solver for day {}", day);
+        return ExitCode::FAILURE;
+    }
+    let mut failed = false;
+    for day in DAYS.iter().filter(|found| day.is_none_or(|day| found.number == day)) {
+        let path = resources.join(day.input);
+        let input = match fs::read_to_string(&path) {
+            Ok(input) => input,
+            Err(error) => {
+                eprintln!("day {:02}: can't read {}: {}", day.number, path.display(), error);
+                failed = true;
+                continue;
+            }
+        };
+        let start = Instant::now();
+        match runner::solve(day.number, &input, parallelism) {
+            Ok(answers) => {
+                println!("day {:02}: {} ({:.2?})", day.number, answers.join(" "), start.elapsed())
+            }
+            Err(error) => {
+                eprintln!("day {:02}: {}", day.number, error);
+                failed = true;
+            }
+        }
+    }
+    match failed {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+}
+
+impl MemoStats {
+    pub fn hit_rate(&self) -> f64 {
+        let calls = self.hits + self.misses;
+        if calls == 0 {
+            0.
+        } else {
+            self.hits as f64 / calls as f64
+        }
+    }
+}
+
+// A cache for recursive functions. The function gets a `recurse` callback to use instead
+// of calling itself, so every sub-call goes through the cache:
+//
+//     let mut memo = Memo::new();
+//     memo.get(n, &|recurse, n| if n < 2 { n } else { recurse(n - 1) + recurse(n - 2) });
+#[derive(Clone, Debug)]
+pub struct Memo<K, V> {
+    cache: HashMap<K, V>,
+    limit: Option<usize>,
+    stats: MemoStats,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            limit: None,
+            stats: MemoStats::default(),
+        }
+    }
+
+    // Once `limit` entries are cached the whole cache is flushed, every entry at once, before
+    // the next result goes in. Memory stays bounded, but nothing survives a flush, however
+    // recently it was used.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::new()
+        }
+    }
+
+    pub fn get<F>(&mut self, key: K, f: &F) -> V
+    where
+        F: Fn(&mut dyn FnMut(K) -> V, K) -> V,
+    {
+        if let Some(value) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return value.clone();
+        }
+        self.stats.misses += 1;
+
+        let mut recurse = |child: K| self.get(child, f);
+        let value = f(&mut recurse, key.clone());
+
+        if self.limit.is_some_and(|limit| self.cache.len() >= limit) {
+            self.stats.evictions += self.cache.len();
+            self.cache.clear();
+        }
+        if self.limit != Some(0) {
+            self.cache.insert(key, value.clone());
+        }
+        value
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memo::Memo;
+
+    fn fibonacci(recurse: &mut dyn FnMut(u64) -> u64, n: u64) -> u64 {
+        if n < 2 {
+            n
+        } else {
+            recurse(n - 1) + recurse(n - 2)
+        }
+    }
+
+    #[test]
+    fn memoises_recursion() {
+        let mut memo = Memo::new();
+        assert_eq!(memo.get(90, &fibonacci), 2880067194370816120);
+        let stats = memo.stats();
+        assert_eq!(stats.misses, 91);
+        assert_eq!(stats.hits, 88);
+        assert_eq!(memo.len(), 91);
+
+        assert_eq!(memo.get(90, &fibonacci), 2880067194370816120);
+        assert_eq!(memo.stats().hits, 89);
+    }
+
+    #[test]
+    fn tuple_keys() {
+        let mut memo = Memo::new();
+        let paths = |recurse: &mut dyn FnMut((u64, u64)) -> u64, (x, y): (u64, u64)| {
+            if x == 0 || y == 0 {
+                1
+            } else {
+                recurse((x - 1, y)) + recurse((x, y - 1))
+            }
+        };
+        assert_eq!(memo.get((16, 16), &paths), 601080390);
+    }
+
+    #[test]
+    fn respects_limit() {
+        let mut memo = Memo::with_limit(10);
+        assert_eq!(memo.get(30, &fibonacci), 832040);
+        assert!(memo.len() <= 10);
+        assert!(memo.stats().evictions > 0);
+        assert!(memo.stats().hit_rate() > 0.);
+
+        let mut memo = Memo::with_limit(0);
+        assert_eq!(memo.get(10, &fibonacci), 55);
+        assert!(memo.is_empty());
+    }
+}
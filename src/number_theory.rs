@@ -0,0 +1,224 @@
+// Exact integer helpers. Anything that could overflow returns None instead of wrapping.
+use std::fmt::Debug;
+
+pub trait Integer: Copy + Ord + Debug {
+    const ZERO: Self;
+    const ONE: Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_div(self, other: Self) -> Option<Self>;
+    fn checked_rem_euclid(self, other: Self) -> Option<Self>;
+    fn checked_abs(self) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),+) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$t>::checked_add(self, other)
+                }
+                fn checked_sub(self, other: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, other)
+                }
+                fn checked_mul(self, other: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, other)
+                }
+                fn checked_div(self, other: Self) -> Option<Self> {
+                    <$t>::checked_div(self, other)
+                }
+                fn checked_rem_euclid(self, other: Self) -> Option<Self> {
+                    <$t>::checked_rem_euclid(self, other)
+                }
+                fn checked_abs(self) -> Option<Self> {
+                    <$t>::checked_abs(self)
+                }
+            }
+        )+
+    };
+}
+
+impl_integer!(i64, i128, isize);
+
+pub fn gcd<T: Integer>(a: T, b: T) -> Option<T> {
+    let mut a = a.checked_abs()?;
+    let mut b = b.checked_abs()?;
+    while b != T::ZERO {
+        let r = a.checked_rem_euclid(b)?;
+        a = b;
+        b = r;
+    }
+    Some(a)
+}
+
+pub fn lcm<T: Integer>(a: T, b: T) -> Option<T> {
+    if a == T::ZERO || b == T::ZERO {
+        return Some(T::ZERO);
+    }
+    let g = gcd(a, b)?;
+    a.checked_div(g)?.checked_mul(b)?.checked_abs()
+}
+
+// Returns (g, x, y) with a * x + b * y = g = gcd(a, b)
+pub fn extended_gcd<T: Integer>(a: T, b: T) -> Option<(T, T, T)> {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+    while r != T::ZERO {
+        let quotient = old_r.checked_div(r)?;
+        (old_r, r) = (r, old_r.checked_sub(quotient.checked_mul(r)?)?);
+        (old_x, x) = (x, old_x.checked_sub(quotient.checked_mul(x)?)?);
+        (old_y, y) = (y, old_y.checked_sub(quotient.checked_mul(y)?)?);
+    }
+    if old_r < T::ZERO {
+        let minus_one = T::ZERO.checked_sub(T::ONE)?;
+        old_r = old_r.checked_mul(minus_one)?;
+        old_x = old_x.checked_mul(minus_one)?;
+        old_y = old_y.checked_mul(minus_one)?;
+    }
+    Some((old_r, old_x, old_y))
+}
+
+pub fn mod_inverse<T: Integer>(a: T, modulus: T) -> Option<T> {
+    if modulus <= T::ZERO {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a.checked_rem_euclid(modulus)?, modulus)?;
+    if g != T::ONE {
+        return None;
+    }
+    x.checked_rem_euclid(modulus)
+}
+
+// a * b mod m, falling back to double-and-add when the product itself would overflow
+pub fn mod_mul<T: Integer>(a: T, b: T, modulus: T) -> Option<T> {
+    if modulus <= T::ZERO {
+        return None;
+    }
+    let a = a.checked_rem_euclid(modulus)?;
+    let mut b = b.checked_rem_euclid(modulus)?;
+    if let Some(product) = a.checked_mul(b) {
+        return product.checked_rem_euclid(modulus);
+    }
+
+    let two = T::ONE.checked_add(T::ONE)?;
+    let mut result = T::ZERO;
+    let mut addend = a;
+    while b > T::ZERO {
+        if b.checked_rem_euclid(two)? == T::ONE {
+            result = mod_add(result, addend, modulus)?;
+        }
+        addend = mod_add(addend, addend, modulus)?;
+        b = b.checked_div(two)?;
+    }
+    Some(result)
+}
+
+// a + b mod m for a, b already in [0, m), without ever going above m
+fn mod_add<T: Integer>(a: T, b: T, modulus: T) -> Option<T> {
+    let room = modulus.checked_sub(b)?;
+    if a >= room {
+        a.checked_sub(room)
+    } else {
+        a.checked_add(b)
+    }
+}
+
+pub fn mod_pow<T: Integer>(base: T, exponent: T, modulus: T) -> Option<T> {
+    if modulus <= T::ZERO || exponent < T::ZERO {
+        return None;
+    }
+    let two = T::ONE.checked_add(T::ONE)?;
+    let mut result = T::ONE.checked_rem_euclid(modulus)?;
+    let mut base = base.checked_rem_euclid(modulus)?;
+    let mut exponent = exponent;
+    while exponent > T::ZERO {
+        if exponent.checked_rem_euclid(two)? == T::ONE {
+            result = mod_mul(result, base, modulus)?;
+        }
+        base = mod_mul(base, base, modulus)?;
+        exponent = exponent.checked_div(two)?;
+    }
+    Some(result)
+}
+
+// Chinese remainder theorem over (residue, modulus) pairs. The moduli don't have to be
+// coprime. Returns (x, m) such that every congruence holds exactly when n = x (mod m), or
+// None if the congruences contradict each other.
+pub fn crt<T: Integer>(congruences: &[(T, T)]) -> Option<(T, T)> {
+    let mut x = T::ZERO;
+    let mut m = T::ONE;
+    for &(residue, modulus) in congruences {
+        if modulus <= T::ZERO {
+            return None;
+        }
+        let residue = residue.checked_rem_euclid(modulus)?;
+        // solve x + m * k = residue (mod modulus) for k
+        let (g, p, _) = extended_gcd(m, modulus)?;
+        let difference = residue.checked_sub(x)?;
+        if difference.checked_rem_euclid(g)? != T::ZERO {
+            return None;
+        }
+        let step = modulus.checked_div(g)?;
+        let k = mod_mul(difference.checked_div(g)?, p, step)?;
+        x = x.checked_add(m.checked_mul(k)?)?;
+        m = m.checked_mul(step)?;
+        x = x.checked_rem_euclid(m)?;
+    }
+    Some((x, m))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::number_theory::{crt, extended_gcd, gcd, lcm, mod_inverse, mod_mul, mod_pow};
+
+    #[test]
+    fn gcd_and_lcm() {
+        assert_eq!(gcd(12i64, 18), Some(6));
+        assert_eq!(gcd(-12i64, 18), Some(6));
+        assert_eq!(gcd(0i64, 0), Some(0));
+        assert_eq!(gcd(i64::MIN, 0), None);
+        assert_eq!(lcm(4i64, 6), Some(12));
+        assert_eq!(lcm(101i128, 103), Some(10403));
+        assert_eq!(lcm(i64::MAX, i64::MAX - 1), None);
+    }
+
+    #[test]
+    fn extended_gcd_satisfies_bezout() {
+        for (a, b) in [(240i64, 46), (-240, 46), (17, 0), (0, 5), (94, 22)] {
+            let (g, x, y) = extended_gcd(a, b).unwrap();
+            assert_eq!(g, gcd(a, b).unwrap());
+            assert_eq!(a * x + b * y, g);
+        }
+    }
+
+    #[test]
+    fn inverses() {
+        assert_eq!(mod_inverse(3i64, 11), Some(4));
+        assert_eq!(mod_inverse(-3i64, 11), Some(7));
+        assert_eq!(mod_inverse(6i64, 9), None);
+        assert_eq!(mod_inverse(101i64, 103).map(|i| i * 101 % 103), Some(1));
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(mod_pow(2i64, 10, 1000), Some(24));
+        assert_eq!(mod_pow(5i64, 0, 1), Some(0));
+        assert_eq!(mod_pow(2i64, -1, 7), None);
+        let big = i64::MAX - 24; // 9223372036854775783 is prime
+        assert_eq!(mod_pow(3i64, big - 1, big), Some(1));
+        assert_eq!(mod_mul(i128::MAX - 1, i128::MAX - 1, i128::MAX), Some(1));
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt(&[(2i64, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1i64, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1i64, 4), (2, 6)]), None);
+        assert_eq!(crt::<i64>(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(-1i128, 101), (-1, 103)]), Some((10402, 10403)));
+    }
+}
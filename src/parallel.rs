@@ -0,0 +1,136 @@
+// Opt-in parallelism for solvers that check lots of independent things, e.g. one calibration
+// line or one claw machine at a time. Work is split into one contiguous chunk per worker on
+// scoped threads and the results come back in input order, so a parallel run gives exactly
+// what the sequential one does.
+//
+// Under a step budget (see budget::limit) the items are mapped sequentially on the caller's
+// thread, so the budget runs out on exactly the item it would have without any workers.
+use crate::budget;
+use std::num::NonZeroUsize;
+use std::thread;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parallelism {
+    workers: NonZeroUsize,
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Self::sequential()
+    }
+}
+
+impl Parallelism {
+    pub const fn sequential() -> Self {
+        Self {
+            workers: NonZeroUsize::MIN,
+        }
+    }
+
+    // Zero workers means sequential as well
+    pub fn with_workers(workers: usize) -> Self {
+        NonZeroUsize::new(workers).map_or(Self::sequential(), |workers| Self { workers })
+    }
+
+    // One worker per core, or sequential if the core count isn't known
+    pub fn available() -> Self {
+        thread::available_parallelism().map_or(Self::sequential(), |workers| Self { workers })
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.get()
+    }
+
+    pub fn is_sequential(&self) -> bool {
+        self.workers() == 1
+    }
+
+    pub fn map<T, R, F>(&self, items: &[T], f: F) -> Vec<R>
+    where
+        T: Sync,
+        R: Send,
+        F: Fn(&T) -> R + Sync,
+    {
+        if self.is_sequential() || items.len() < 2 || budget::remaining().is_some() {
+            return items.iter().map(f).collect();
+        }
+        let chunk_size = items.len().div_ceil(self.workers());
+        let f = &f;
+        thread::scope(|scope| {
+            let handles = items
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+            let mut results = Vec::with_capacity(items.len());
+            for handle in handles {
+                match handle.join() {
+                    Ok(chunk) => results.extend(chunk),
+                    Err(panic) => std::panic::resume_unwind(panic),
+                }
+            }
+            results
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::{self, limit};
+    use crate::error::AocError;
+    use crate::parallel::Parallelism;
+
+    #[test]
+    fn keeps_input_order() {
+        let items = (0..1000).collect::<Vec<u64>>();
+        let right = items.iter().map(|n| n * n).collect::<Vec<_>>();
+        for workers in [0, 1, 2, 3, 7, 64, 2000] {
+            let left = Parallelism::with_workers(workers).map(&items, |n| n * n);
+            assert_eq!(left, right, "{} workers", workers);
+        }
+    }
+
+    #[test]
+    fn worker_counts() {
+        assert!(Parallelism::with_workers(0).is_sequential());
+        assert_eq!(Parallelism::with_workers(4).workers(), 4);
+        assert_eq!(Parallelism::default(), Parallelism::sequential());
+        assert!(Parallelism::available().workers() >= 1);
+    }
+
+    #[test]
+    fn empty_input() {
+        let left = Parallelism::with_workers(4).map(&[] as &[u8], |n| *n);
+        assert!(left.is_empty());
+    }
+
+    #[test]
+    fn matches_sequential_under_a_budget() {
+        let spin = |steps: &usize| (0..*steps).try_for_each(|_| budget::step());
+        let parallel = Parallelism::with_workers(4);
+        let sequential = Parallelism::sequential();
+        for (steps, items) in [
+            (100, vec![5, 10, 15, 20]),
+            (10, vec![11, 1]),
+            (10, vec![6, 6]),
+        ] {
+            let left = limit(steps, || (parallel.map(&items, spin), budget::remaining()));
+            let right = limit(steps, || {
+                (sequential.map(&items, spin), budget::remaining())
+            });
+            assert_eq!(left, right, "{:?} in {} steps", items, steps);
+        }
+        let left = limit(10, || parallel.map(&[6, 6], spin));
+        assert_eq!(left, vec![Ok(()), Err(AocError::OutOfSteps)]);
+        assert_eq!(parallel.map(&[6, 6], spin), vec![Ok(()); 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bad item")]
+    fn passes_panics_on() {
+        Parallelism::with_workers(4).map(&[1, 2, 3, 4], |n| {
+            if *n == 3 {
+                panic!("bad item");
+            }
+        });
+    }
+}
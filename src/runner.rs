@@ -0,0 +1,194 @@
+// Runs each day's solvers on its puzzle input and gives back the answers as text, for the `aoc`
+// binary. Days 2, 6, 7 and 13 check their independent pieces with the parallelism asked for,
+// the rest always run sequentially.
+use crate::arith::{add, mul};
+use crate::day_09::Compress;
+use crate::error::AocError;
+use crate::parallel::Parallelism;
+use crate::{
+    day_01, day_02, day_03, day_04, day_05, day_06, day_07, day_08, day_09, day_10, day_11,
+    day_12, day_13, day_14, day_15, day_16,
+};
+use std::collections::HashSet;
+
+pub struct Day {
+    pub number: u8,
+    // the puzzle input, relative to the resources directory
+    pub input: &'static str,
+}
+
+pub const DAYS: [Day; 16] = [
+    Day { number: 1, input: "day_01/easy.txt" },
+    Day { number: 2, input: "day_02/easy.txt" },
+    Day { number: 3, input: "day_03/easy.txt" },
+    Day { number: 4, input: "day_04/easy.txt" },
+    Day { number: 5, input: "day_05/easy.txt" },
+    Day { number: 6, input: "day_06/easy.txt" },
+    Day { number: 7, input: "day_07/day_07.txt" },
+    Day { number: 8, input: "day_08/easy.txt" },
+    Day { number: 9, input: "day_09/easy.txt" },
+    Day { number: 10, input: "day_10/easy.txt" },
+    Day { number: 11, input: "day_11/easy.txt" },
+    Day { number: 12, input: "day_12/input.txt" },
+    Day { number: 13, input: "day_13/easy.txt" },
+    Day { number: 14, input: "day_14/input.txt" },
+    Day { number: 15, input: "day_15/easy.txt" },
+    Day { number: 16, input: "day_16/easy.txt" },
+];
+
+// The answer to each part of the day that has been solved, in order
+pub fn solve(day: u8, input: &str, parallelism: Parallelism) -> Result<Vec<String>, AocError> {
+    let answers = match day {
+        1 => {
+            let [mut left, mut right] = day_01::parse_input(input)?;
+            let similarity = day_01::get_similarity_score(&left, &right)?;
+            let distance = day_01::find_sorted_differences_sum(&mut left, &mut right)?;
+            vec![distance.to_string(), similarity.to_string()]
+        }
+        2 => {
+            let reports = day_02::parse_input(input)?;
+            vec![
+                day_02::find_number_of_safe_reports_with(&reports, parallelism)?.to_string(),
+                day_02::find_number_of_dampened_safe_reports_with(&reports, parallelism)?
+                    .to_string(),
+            ]
+        }
+        3 => vec![
+            day_03::get_computer_instructions_sum(input)?.to_string(),
+            day_03::get_toggled_computer_instructions_sum(input)?.to_string(),
+        ],
+        4 => {
+            let word_search = day_04::parse_input(input)?;
+            vec![
+                day_04::get_word_count("XMAS", &word_search)?.to_string(),
+                day_04::get_cross_word_count(&word_search)?.to_string(),
+            ]
+        }
+        5 => {
+            let (ordering_rules, page_orders) = day_05::parse_input(input)?;
+            vec![
+                day_05::day_05(&ordering_rules, &page_orders)?.to_string(),
+                day_05::day_05_hard(&ordering_rules, &page_orders)?.to_string(),
+            ]
+        }
+        6 => {
+            let lab = day_06::parse_input(input)?;
+            let patrolled = day_06::find_patrol_path(&lab)?.into_iter().collect::<HashSet<_>>();
+            let obstructions = day_06::find_obstructions_with(&lab, parallelism)?;
+            vec![patrolled.len().to_string(), obstructions.len().to_string()]
+        }
+        7 => {
+            let equations = day_07::parse_input(input)?;
+            vec![
+                day_07::find_sum_with(&equations, parallelism)?.to_string(),
+                day_07::find_sum_hard_with(&equations, parallelism)?.to_string(),
+            ]
+        }
+        8 => {
+            let antennas = day_08::parse_input(input)?;
+            let harmonics = day_08::find_antinodes_hard(&antennas)?;
+            vec![
+                day_08::find_all_anti_nodes(&antennas)?.len().to_string(),
+                harmonics.into_iter().collect::<HashSet<_>>().len().to_string(),
+            ]
+        }
+        9 => {
+            let mut blocks = day_09::parse_input(input)?;
+            blocks.compress_blocks()?;
+            let mut disk = day_09::Disk::parse(input)?;
+            disk.compress_files()?;
+            vec![block_checksum(&blocks)?.to_string(), disk.checksum()?.to_string()]
+        }
+        10 => {
+            let terrain = day_10::parse_input(input)?;
+            vec![
+                day_10::find_score(&terrain)?.to_string(),
+                day_10::find_rating_score(&terrain)?.to_string(),
+            ]
+        }
+        11 => {
+            let stones = day_11::parse_input(input)?;
+            vec![
+                day_11::blink_stones(stones.clone(), 25)?.to_string(),
+                day_11::blink_stones(stones, 75)?.to_string(),
+            ]
+        }
+        12 => {
+            let land = day_12::parse_input(input)?;
+            vec![
+                day_12::get_price(&land)?.to_string(),
+                day_12::get_discounted_price(&land)?.to_string(),
+            ]
+        }
+        13 => {
+            let mut trials = day_13::parse_input(input)?;
+            let near = day_13::fewest_tokens_with(&trials, parallelism)?;
+            day_13::move_prizes(&mut trials, day_13::PRIZE_OFFSET)?;
+            let far = day_13::fewest_tokens_with(&trials, parallelism)?;
+            vec![near.to_string(), far.to_string()]
+        }
+        14 => {
+            let mut robots = day_14::parse_input(input)?;
+            let aligned = day_14::find_alignment_time(&robots, &day_14::BATHROOM)?;
+            let safety = day_14::check_safety(&mut robots, 100, &day_14::BATHROOM)?;
+            vec![safety.to_string(), aligned.to_string()]
+        }
+        15 => {
+            let (mut warehouse, instructions) = day_15::parse_input(input)?;
+            let mut wide_warehouse = warehouse.clone();
+            day_15::update_warehouse(&mut warehouse, &instructions)?;
+            day_15::resize_warehouse(&mut wide_warehouse);
+            day_15::update_wide_warehouse(&mut wide_warehouse, &instructions)?;
+            vec![
+                day_15::count_score(&warehouse)?.to_string(),
+                day_15::count_wide_score(&wide_warehouse)?.to_string(),
+            ]
+        }
+        16 => vec![day_16::get_shortest_path_score(&day_16::parse_input(input)?)?.to_string()],
+        _ => return Err(AocError::domain(format!("there's no solver for day {}", day))),
+    };
+    Ok(answers)
+}
+
+// Each file id times the position of every block it's in, for the blocks as compressed one at
+// a time
+fn block_checksum(blocks: &[Option<usize>]) -> Result<usize, AocError> {
+    blocks.iter().enumerate().try_fold(0, |checksum, (position, block)| match block {
+        Some(id) => add(checksum, mul(*id, position)?),
+        None => Ok(checksum),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AocError;
+    use crate::parallel::Parallelism;
+    use crate::runner::{solve, DAYS};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn preambles() {
+        let preamble = |day: &str| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join(day);
+            fs::read_to_string(path.join("preamble.txt")).unwrap()
+        };
+        let left = solve(13, &preamble("day_13"), Parallelism::sequential());
+        assert_eq!(left.unwrap()[0], "480");
+        let left = solve(15, &preamble("day_15"), Parallelism::sequential());
+        assert_eq!(left.unwrap(), ["10092", "9021"]);
+        assert!(matches!(solve(25, "", Parallelism::sequential()), Err(AocError::Domain(_))));
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        for day in DAYS.iter().filter(|day| [2, 6, 7, 13].contains(&day.number)) {
+            let input = fs::read_to_string(resources.join(day.input)).unwrap();
+            let right = solve(day.number, &input, Parallelism::sequential());
+            assert!(right.is_ok(), "day {}", day.number);
+            let left = solve(day.number, &input, Parallelism::with_workers(4));
+            assert_eq!(left, right, "day {}", day.number);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::AocError;
+use crate::graph::Index;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+pub trait Tile: Sized + Copy {
+    fn from_char(ch: char) -> Option<Self>;
+    fn to_char(&self) -> char;
+}
+
+// Declares a tile enum along with its character mapping, e.g.
+// tile! { pub enum LabTile { Floor => '.', Obstruction => '#' } }
+macro_rules! tile {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident => $ch:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($variant),+
+        }
+
+        impl $crate::tile::Tile for $name {
+            fn from_char(ch: char) -> Option<Self> {
+                match ch {
+                    $($ch => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+
+            fn to_char(&self) -> char {
+                match self {
+                    $($name::$variant => $ch,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", $crate::tile::Tile::to_char(self))
+            }
+        }
+    };
+}
+pub(crate) use tile;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UnknownTileError {
+    pub ch: char,
+    pub index: Index,
+}
+
+impl Display for UnknownTileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown tile {:?} at row {}, column {}",
+            self.ch, self.index.y, self.index.x
+        )
+    }
+}
+
+impl Error for UnknownTileError {}
+
+pub fn parse_grid<T: Tile>(input: &str) -> Result<Vec<Vec<T>>, UnknownTileError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(row, line)| {
+            line.chars()
+                .enumerate()
+                .map(|(column, ch)| {
+                    T::from_char(ch).ok_or(UnknownTileError {
+                        ch,
+                        index: Index { x: column, y: row },
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The puzzles' grids are all rectangles, a short or long row means the input is broken
+pub fn rectangular<T>(grid: Vec<Vec<T>>) -> Result<Vec<Vec<T>>, AocError> {
+    let width = grid.first().map_or(0, Vec::len);
+    match grid.iter().position(|row| row.len() != width) {
+        Some(row) => Err(AocError::parse(format!(
+            "row {} is {} wide, the first row is {}",
+            row,
+            grid[row].len(),
+            width
+        ))),
+        None => Ok(grid),
+    }
+}
+
+pub fn render_grid<T: Tile>(grid: &[Vec<T>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().map(Tile::to_char).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Index;
+    use crate::error::AocError;
+    use crate::tile::{parse_grid, rectangular, render_grid, Tile, UnknownTileError};
+
+    tile! {
+        enum TestTile {
+            Floor => '.',
+            Wall => '#',
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let input = "#.#\n...\n#.#";
+        let grid = parse_grid::<TestTile>(input).unwrap();
+        assert_eq!(grid[0][0], TestTile::Wall);
+        assert_eq!(grid[1][1], TestTile::Floor);
+        assert_eq!(render_grid(&grid), input);
+    }
+
+    #[test]
+    fn reports_unknown_tile_position() {
+        let left = parse_grid::<TestTile>("#.#\n.x.");
+        let right = Err(UnknownTileError {
+            ch: 'x',
+            index: Index { x: 1, y: 1 },
+        });
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn maps_characters_both_ways() {
+        assert_eq!(TestTile::from_char('#'), Some(TestTile::Wall));
+        assert_eq!(TestTile::from_char('?'), None);
+        assert_eq!(TestTile::Floor.to_char(), '.');
+        assert_eq!(TestTile::Wall.to_string(), "#");
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let grid = parse_grid::<TestTile>("#.#\n..\n#.#").unwrap();
+        let left = rectangular(grid);
+        let right = Err(AocError::parse("row 1 is 2 wide, the first row is 3"));
+        assert_eq!(left, right);
+        assert!(rectangular(parse_grid::<TestTile>("#.\n.#").unwrap()).is_ok());
+    }
+}
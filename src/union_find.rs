@@ -0,0 +1,339 @@
+use crate::arith::{add, mul};
+use crate::budget;
+use crate::error::AocError;
+use crate::graph::Index;
+
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    n_sets: usize,
+}
+
+impl UnionFind {
+    pub fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            size: vec![1; n],
+            n_sets: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn n_sets(&self) -> usize {
+        self.n_sets
+    }
+
+    pub fn is_root(&self, x: usize) -> bool {
+        self.parent[x] == x
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // compress the path so every node on it points straight at the root
+        let mut current = x;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    // Returns (root, absorbed) when two different sets were merged, where absorbed is the
+    // root that stopped being one.
+    pub fn union(&mut self, a: usize, b: usize) -> Option<(usize, usize)> {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            return None;
+        }
+        let (root, absorbed) = if self.rank[a] < self.rank[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        if self.rank[root] == self.rank[absorbed] {
+            self.rank[root] += 1;
+        }
+        self.parent[absorbed] = root;
+        self.size[root] += self.size[absorbed];
+        self.n_sets -= 1;
+        Some((root, absorbed))
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    // Turns every element of one set back into a singleton. `members` must be the whole set,
+    // otherwise elements outside it could be left pointing at a reset node.
+    pub fn dissolve(&mut self, members: &[usize]) {
+        members.iter().for_each(|&x| {
+            self.parent[x] = x;
+            self.rank[x] = 0;
+            self.size[x] = 1;
+        });
+        self.n_sets += members.len().saturating_sub(1);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionStats<'a, T> {
+    pub tile: &'a T,
+    pub root: Index,
+    pub area: usize,
+    pub perimeter: usize,
+}
+
+// Groups equal, edge-adjacent cells of a grid into regions and keeps their area and
+// perimeter up to date as cells are edited.
+#[derive(Clone, Debug)]
+pub struct GridRegions<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+    sets: UnionFind,
+    area: Vec<usize>,
+    perimeter: Vec<usize>,
+    members: Vec<Vec<usize>>,
+}
+
+impl<T: Eq> GridRegions<T> {
+    pub fn new(grid: &[Vec<T>]) -> Self
+    where
+        T: Clone,
+    {
+        let height = grid.len();
+        let width = grid.first().map_or(0, Vec::len);
+        let cells: Vec<T> = grid.iter().flatten().cloned().collect();
+        let n = cells.len();
+
+        let mut regions = Self {
+            width,
+            height,
+            cells,
+            sets: UnionFind::new(n),
+            area: vec![1; n],
+            perimeter: vec![0; n],
+            members: (0..n).map(|cell| vec![cell]).collect(),
+        };
+        for cell in 0..n {
+            regions.perimeter[cell] = regions.fences(cell);
+        }
+        for cell in 0..n {
+            // looking right and down is enough to see every shared edge once
+            if !(cell + 1).is_multiple_of(width) && regions.cells[cell] == regions.cells[cell + 1] {
+                regions.merge(cell, cell + 1);
+            }
+            if cell + width < n && regions.cells[cell] == regions.cells[cell + width] {
+                regions.merge(cell, cell + width);
+            }
+        }
+        regions
+    }
+
+    fn offset(&self, index: &Index) -> Option<usize> {
+        if index.x < self.width && index.y < self.height {
+            Some(index.y * self.width + index.x)
+        } else {
+            None
+        }
+    }
+
+    fn index_of(&self, offset: usize) -> Index {
+        Index {
+            x: offset % self.width,
+            y: offset / self.width,
+        }
+    }
+
+    fn neighbours(&self, cell: usize) -> impl Iterator<Item = usize> {
+        let width = self.width;
+        let n = self.cells.len();
+        [
+            cell.checked_sub(width),
+            (!cell.is_multiple_of(width)).then(|| cell - 1),
+            (!(cell + 1).is_multiple_of(width)).then_some(cell + 1),
+            (cell + width < n).then_some(cell + width),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    // Number of fence sides this cell contributes to its region's perimeter
+    fn fences(&self, cell: usize) -> usize {
+        let same = self
+            .neighbours(cell)
+            .filter(|&neighbour| self.cells[neighbour] == self.cells[cell])
+            .count();
+        4 - same
+    }
+
+    fn merge(&mut self, a: usize, b: usize) {
+        if let Some((root, absorbed)) = self.sets.union(a, b) {
+            self.area[root] += self.area[absorbed];
+            self.perimeter[root] += self.perimeter[absorbed];
+            let mut absorbed_members = std::mem::take(&mut self.members[absorbed]);
+            if absorbed_members.len() > self.members[root].len() {
+                std::mem::swap(&mut absorbed_members, &mut self.members[root]);
+            }
+            self.members[root].extend(absorbed_members);
+        }
+    }
+
+    pub fn get(&self, index: &Index) -> Option<&T> {
+        self.cells.get(self.offset(index)?)
+    }
+
+    pub fn region_of(&mut self, index: &Index) -> Option<RegionStats<'_, T>> {
+        let offset = self.offset(index)?;
+        let root = self.sets.find(offset);
+        Some(self.stats(root))
+    }
+
+    fn stats(&self, root: usize) -> RegionStats<'_, T> {
+        RegionStats {
+            tile: &self.cells[root],
+            root: self.index_of(root),
+            area: self.area[root],
+            perimeter: self.perimeter[root],
+        }
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = RegionStats<'_, T>> + '_ {
+        (0..self.cells.len())
+            .filter(|&cell| self.sets.is_root(cell))
+            .map(|root| self.stats(root))
+    }
+
+    pub fn n_regions(&self) -> usize {
+        self.sets.n_sets()
+    }
+
+    pub fn price(&self) -> Result<usize, AocError> {
+        self.regions().try_fold(0, |total, region| {
+            budget::step()?;
+            add(total, mul(region.area, region.perimeter)?)
+        })
+    }
+
+    // Changes one cell and rebuilds only the region it used to belong to, merging it into
+    // any neighbouring regions of the new type. Returns the previous value.
+    pub fn set(&mut self, index: &Index, value: T) -> Option<T> {
+        let cell = self.offset(index)?;
+        if self.cells[cell] == value {
+            return Some(value);
+        }
+
+        // neighbours outside the old region keep their region but gain or lose a fence
+        let outside = self
+            .neighbours(cell)
+            .filter(|&neighbour| self.cells[neighbour] != self.cells[cell])
+            .collect::<Vec<_>>();
+        for &neighbour in &outside {
+            let root = self.sets.find(neighbour);
+            self.perimeter[root] -= self.fences(neighbour);
+        }
+
+        let old_root = self.sets.find(cell);
+        let dissolved = std::mem::take(&mut self.members[old_root]);
+        self.sets.dissolve(&dissolved);
+        let previous = std::mem::replace(&mut self.cells[cell], value);
+
+        for &neighbour in &outside {
+            let root = self.sets.find(neighbour);
+            self.perimeter[root] += self.fences(neighbour);
+        }
+        for &member in &dissolved {
+            self.area[member] = 1;
+            self.perimeter[member] = self.fences(member);
+            self.members[member] = vec![member];
+        }
+        for &member in &dissolved {
+            let same = self
+                .neighbours(member)
+                .filter(|&neighbour| self.cells[neighbour] == self.cells[member])
+                .collect::<Vec<_>>();
+            same.into_iter()
+                .for_each(|neighbour| self.merge(member, neighbour));
+        }
+        Some(previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Index;
+    use crate::union_find::{GridRegions, UnionFind};
+
+    const PREAMBLE: &str = include_str!("../resources/day_12/preamble.txt");
+
+    fn parse_input(input: &str) -> Vec<Vec<char>> {
+        input.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn unions_and_finds() {
+        let mut sets = UnionFind::new(5);
+        assert!(sets.union(0, 1).is_some());
+        assert!(sets.union(3, 4).is_some());
+        assert!(sets.union(1, 0).is_none());
+        assert!(sets.connected(0, 1));
+        assert!(!sets.connected(1, 3));
+        assert_eq!(sets.size_of(4), 2);
+        assert_eq!(sets.n_sets(), 3);
+    }
+
+    #[test]
+    fn preamble_price() {
+        let regions = GridRegions::new(&parse_input(PREAMBLE));
+        assert_eq!(regions.n_regions(), 11);
+        assert_eq!(regions.price(), Ok(1930));
+    }
+
+    #[test]
+    fn edits_match_a_fresh_build() {
+        let mut garden = parse_input(PREAMBLE);
+        let mut regions = GridRegions::new(&garden);
+        let edits = [(0, 0, 'X'), (4, 3, 'C'), (6, 5, 'X'), (2, 2, 'V'), (0, 0, 'R'), (9, 9, 'I')];
+        for (x, y, tile) in edits {
+            let index = Index { x, y };
+            regions.set(&index, tile);
+            garden[y][x] = tile;
+
+            let fresh = GridRegions::new(&garden);
+            assert_eq!(regions.n_regions(), fresh.n_regions());
+            assert_eq!(regions.price(), fresh.price());
+        }
+    }
+
+    #[test]
+    fn splitting_a_region() {
+        let mut regions = GridRegions::new(&parse_input("AAA\nBBB"));
+        assert_eq!(regions.n_regions(), 2);
+        regions.set(&Index { x: 1, y: 0 }, 'B');
+        assert_eq!(regions.n_regions(), 3);
+        let region = regions.region_of(&Index { x: 1, y: 0 }).unwrap();
+        assert_eq!(region.area, 4);
+        assert_eq!(region.perimeter, 10);
+        assert_eq!(regions.region_of(&Index { x: 0, y: 0 }).unwrap().area, 1);
+    }
+}